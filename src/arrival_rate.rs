@@ -0,0 +1,48 @@
+use nanoserde::DeRon;
+
+use std::time::Duration;
+
+// in an open-model situation, the rate at which new persona iterations are launched, regardless
+// of how quickly the service under test is responding to the ones already in flight
+#[derive(Clone, Debug, DeRon)]
+pub enum ArrivalRate {
+    PerSecond(u32),
+    PerMinute(u32),
+}
+
+impl ArrivalRate {
+    /// the time between two consecutive arrivals, or None for a rate of zero
+    pub fn interval(&self) -> Option<Duration> {
+        let (count, per) = match self {
+            Self::PerSecond(count) => (*count, Duration::from_secs(1)),
+            Self::PerMinute(count) => (*count, Duration::from_secs(60)),
+        };
+
+        if count == 0 {
+            None
+        } else {
+            Some(per / count)
+        }
+    }
+}
+
+#[test]
+fn test_per_second() {
+    assert_eq!(
+        Some(Duration::from_millis(50)),
+        ArrivalRate::PerSecond(20).interval()
+    );
+}
+
+#[test]
+fn test_per_minute() {
+    assert_eq!(
+        Some(Duration::from_secs(2)),
+        ArrivalRate::PerMinute(30).interval()
+    );
+}
+
+#[test]
+fn test_zero() {
+    assert_eq!(None, ArrivalRate::PerSecond(0).interval());
+}
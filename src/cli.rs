@@ -1,15 +1,111 @@
 use argh::FromArgs;
 
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use crate::compare::Tolerances;
+use crate::config_duration::ConfigDuration;
+use crate::interpolation::{SetVariable, Variables};
 use crate::situation::SituationSpec;
+use crate::targets::TargetOverride;
+use crate::timeseries::TimeSeriesFormat;
 
-/// situational-mock-based load testing
+/// situational-mock-based load testing (see also: seatrial lint --help, seatrial compare --help)
 #[derive(FromArgs)]
 struct CmdArgsBase {
     /// integral multiplier for grunt counts (minimum 1)
     #[argh(option, short = 'm', default = "1")]
     multiplier: usize,
 
-    /// base URL for all situations in this run
+    /// wall-clock budget for the run (eg. 90s, 30m, 2h), after which grunts finish their current
+    /// step and exit; situations may set their own duration instead
+    #[argh(option)]
+    duration: Option<ConfigDuration>,
+
+    /// print the fully resolved requests each grunt would send, walking each sequence once,
+    /// rather than sending them
+    #[argh(switch)]
+    dry_run: bool,
+
+    /// how many threads to run grunts on (default: one per grunt, up to 256); each thread
+    /// interleaves its grunts a step at a time and waits out each of their requests, so this caps
+    /// how many requests are in flight
+    #[argh(option)]
+    workers: Option<usize>,
+
+    /// seed for Lua's math.random, from which each grunt's own seed is derived; a random seed is
+    /// chosen (and printed) if not given
+    #[argh(option)]
+    seed: Option<u64>,
+
+    /// replace the URL of a named target in every situation (eg. auth=http://localhost:9090);
+    /// may be given more than once
+    #[argh(option)]
+    target: Vec<TargetOverride>,
+
+    /// define a variable for ${VAR} placeholders in situation configs (eg. TOKEN=abc123),
+    /// taking precedence over the environment; may be given more than once
+    #[argh(option)]
+    set: Vec<SetVariable>,
+
+    /// write a line of JSON describing each pipeline step taken to this file (see seatrial(1)
+    /// for its fields)
+    #[argh(option)]
+    events: Option<PathBuf>,
+
+    /// print a line per situation every so often (eg. 10s) with its active grunts, throughput,
+    /// error rate, and latencies since the previous line
+    #[argh(option)]
+    progress: Option<ConfigDuration>,
+
+    /// serve metrics in Prometheus' text format at /metrics on this address (eg. 0.0.0.0:9100)
+    /// for as long as the run goes
+    #[argh(option)]
+    metrics_listen: Option<SocketAddr>,
+
+    /// send metrics about each step and grunt to the StatsD agent at this address (eg.
+    /// localhost:8125) over UDP
+    #[argh(option)]
+    statsd: Option<String>,
+
+    /// prefix for the names of metrics sent with --statsd (default: seatrial)
+    #[argh(option, default = "String::from(\"seatrial\")")]
+    statsd_prefix: String,
+
+    /// send --statsd metrics with DogStatsD tags, rather than folding situation, persona, and so
+    /// on into metric names
+    #[argh(switch)]
+    dogstatsd: bool,
+
+    /// write a JUnit XML report of each situation's Validator and Combinator steps to this file
+    /// once the run is over
+    #[argh(option)]
+    junit: Option<PathBuf>,
+
+    /// print up to this many warnings a second as they're raised (default: none); every warning
+    /// is tallied in a table at the end of the run regardless
+    #[argh(option, default = "0")]
+    warning_rate: u32,
+
+    /// write a JSON report of each step's throughput, error rate, and latencies to this file once
+    /// the run is over, for use with seatrial compare
+    #[argh(option)]
+    report: Option<PathBuf>,
+
+    /// write throughput, error counts, and latencies of each step, bucketed into fixed windows
+    /// by when requests started, to this file once the run is over
+    #[argh(option)]
+    timeseries: Option<PathBuf>,
+
+    /// width of --timeseries windows (default: 1s)
+    #[argh(option, default = "ConfigDuration::Seconds(1)")]
+    timeseries_window: ConfigDuration,
+
+    /// format of the --timeseries file, csv or json (default: csv)
+    #[argh(option, default = "TimeSeriesFormat::Csv")]
+    timeseries_format: TimeSeriesFormat,
+
+    /// base URL for situations that don't set their own base_url
     #[argh(positional)]
     base_url: String,
 
@@ -19,11 +115,11 @@ struct CmdArgsBase {
     // straightforward
     /// path to a RON file in seatrial(5) situation config format
     #[argh(positional)]
-    req_situation: SituationSpec,
+    req_situation: String,
 
     /// optional paths to additional RON files in seatrial(5) situation config format
     #[argh(positional)]
-    situations: Vec<SituationSpec>,
+    situations: Vec<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -31,7 +127,52 @@ pub struct CmdArgs {
     /// integral multiplier for grunt counts (minimum 1)
     pub multiplier: usize,
 
-    /// base URL for all situations in this run
+    /// wall-clock budget for situations that don't set their own duration
+    pub duration: Option<ConfigDuration>,
+
+    /// print requests rather than sending them
+    pub dry_run: bool,
+
+    /// how many threads to run grunts on
+    pub workers: Option<usize>,
+
+    /// seed from which each grunt's Lua math.random seed is derived
+    pub seed: Option<u64>,
+
+    /// named target URLs replacing those set by situations
+    pub targets: Vec<TargetOverride>,
+
+    /// where to write a JSON Lines stream of step events, if anywhere
+    pub events: Option<PathBuf>,
+
+    /// how often to print progress lines, if at all
+    pub progress: Option<ConfigDuration>,
+
+    /// where to serve Prometheus metrics, if anywhere
+    pub metrics_listen: Option<SocketAddr>,
+
+    /// where to send StatsD metrics, if anywhere, under what prefix, and whether with DogStatsD
+    /// tags
+    pub statsd: Option<String>,
+    pub statsd_prefix: String,
+    pub dogstatsd: bool,
+
+    /// where to write a JUnit XML report of validations, if anywhere
+    pub junit: Option<PathBuf>,
+
+    /// how many warnings a second to print as they're raised
+    pub warning_rate: u32,
+
+    /// where to write a JSON run report, if anywhere
+    pub report: Option<PathBuf>,
+
+    /// where to write a time series of results, if anywhere, how wide its windows are, and in
+    /// what format
+    pub timeseries: Option<PathBuf>,
+    pub timeseries_window: ConfigDuration,
+    pub timeseries_format: TimeSeriesFormat,
+
+    /// base URL for situations that don't set their own base_url
     pub base_url: String,
 
     /// paths to RON files in seatrial(5) situation config format
@@ -41,16 +182,172 @@ pub struct CmdArgs {
 /// flatten situations into a single vec (see docs about CmdArgsBase::req_situation)
 impl From<CmdArgsBase> for CmdArgs {
     fn from(mut it: CmdArgsBase) -> Self {
-        it.situations.insert(0, it.req_situation.clone());
+        it.situations.insert(0, it.req_situation);
 
         Self {
             multiplier: it.multiplier,
+            duration: it.duration,
+            dry_run: it.dry_run,
+            workers: it.workers,
+            seed: it.seed,
+            targets: it.target,
+            events: it.events,
+            progress: it.progress,
+            metrics_listen: it.metrics_listen,
+            statsd: it.statsd,
+            statsd_prefix: it.statsd_prefix,
+            dogstatsd: it.dogstatsd,
+            junit: it.junit,
+            warning_rate: it.warning_rate,
+            report: it.report,
+            timeseries: it.timeseries,
+            timeseries_window: it.timeseries_window,
+            timeseries_format: it.timeseries_format,
             base_url: it.base_url,
-            situations: it.situations,
+            situations: load_situations(&it.situations, &Variables::new(&it.set)),
+        }
+    }
+}
+
+/// statically check situation configs and their Lua user scripts for problems, without sending
+/// any traffic
+#[derive(FromArgs)]
+struct LintArgsBase {
+    /// define a variable for ${VAR} placeholders in situation configs (eg. TOKEN=abc123),
+    /// taking precedence over the environment; may be given more than once
+    #[argh(option)]
+    set: Vec<SetVariable>,
+
+    // see docs about CmdArgsBase::req_situation
+    /// path to a RON file in seatrial(5) situation config format
+    #[argh(positional)]
+    req_situation: String,
+
+    /// optional paths to additional RON files in seatrial(5) situation config format
+    #[argh(positional)]
+    situations: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct LintArgs {
+    /// paths to RON files in seatrial(5) situation config format
+    pub situations: Vec<SituationSpec>,
+}
+
+/// flatten situations into a single vec (see docs about CmdArgsBase::req_situation)
+impl From<LintArgsBase> for LintArgs {
+    fn from(mut it: LintArgsBase) -> Self {
+        it.situations.insert(0, it.req_situation);
+
+        Self {
+            situations: load_situations(&it.situations, &Variables::new(&it.set)),
         }
     }
 }
 
-pub fn parse_args() -> CmdArgs {
-    argh::from_env::<CmdArgsBase>().into()
+/// compare two run reports written with --report, step by step, optionally failing if the
+/// candidate regressed beyond the given tolerances
+#[derive(FromArgs)]
+struct CompareArgsBase {
+    /// fail if any of a step's p50, p95, or p99 latencies rose by more than this many percent
+    #[argh(option)]
+    latency_tolerance: Option<f64>,
+
+    /// fail if a step's error rate rose by more than this many percentage points
+    #[argh(option)]
+    error_rate_tolerance: Option<f64>,
+
+    /// fail if a step's throughput dropped by more than this many percent
+    #[argh(option)]
+    throughput_tolerance: Option<f64>,
+
+    /// path to the report of the run to compare against
+    #[argh(positional)]
+    baseline: PathBuf,
+
+    /// path to the report of the run being compared
+    #[argh(positional)]
+    candidate: PathBuf,
+}
+
+#[derive(Clone, Debug)]
+pub struct CompareArgs {
+    /// paths to the reports to compare
+    pub baseline: PathBuf,
+    pub candidate: PathBuf,
+
+    /// how far the candidate may fall behind the baseline
+    pub tolerances: Tolerances,
+}
+
+impl From<CompareArgsBase> for CompareArgs {
+    fn from(it: CompareArgsBase) -> Self {
+        Self {
+            baseline: it.baseline,
+            candidate: it.candidate,
+            tolerances: Tolerances {
+                latency: it.latency_tolerance,
+                error_rate: it.error_rate_tolerance,
+                throughput: it.throughput_tolerance,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Command {
+    Run(Box<CmdArgs>),
+    Lint(LintArgs),
+    Compare(CompareArgs),
+}
+
+// argh's subcommand support can't be mixed with the required positionals of a plain run (it
+// would treat "lint" as a base_url), so subcommands are picked off by hand before handing the
+// remaining arguments to argh
+pub fn parse_args() -> Command {
+    let strings: Vec<String> = std::env::args().collect();
+    let strs: Vec<&str> = strings.iter().map(String::as_str).collect();
+
+    match strs.get(1) {
+        Some(&"lint") => Command::Lint(parse_subcommand_args::<LintArgsBase>(&strs).into()),
+        Some(&"compare") => {
+            Command::Compare(parse_subcommand_args::<CompareArgsBase>(&strs).into())
+        }
+        _ => Command::Run(Box::new(argh::from_env::<CmdArgsBase>().into())),
+    }
+}
+
+// a subcommand-flavoured copy of argh::from_env, which expects the subcommand name in strs[1]
+fn parse_subcommand_args<T: FromArgs>(strs: &[&str]) -> T {
+    let cmd = strs[0].rsplit('/').next().unwrap_or(strs[0]);
+
+    T::from_args(&[cmd, strs[1]], &strs[2..]).unwrap_or_else(|early_exit| {
+        std::process::exit(match early_exit.status {
+            Ok(()) => {
+                println!("{}", early_exit.output);
+                0
+            }
+            Err(()) => {
+                eprintln!(
+                    "{}\nRun {} {} --help for more information.",
+                    early_exit.output, cmd, strs[1]
+                );
+                1
+            }
+        })
+    })
+}
+
+// situations can only be parsed once every --set has been, so unlike the rest of the arguments,
+// they're loaded after argh is done. failures exit the process as argh's own would
+fn load_situations(paths: &[String], vars: &Variables) -> Vec<SituationSpec> {
+    paths
+        .iter()
+        .map(|path| {
+            SituationSpec::load(path, vars).unwrap_or_else(|err| {
+                eprintln!("Error loading situation '{}': {}", path, err);
+                std::process::exit(1);
+            })
+        })
+        .collect()
 }
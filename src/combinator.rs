@@ -59,6 +59,9 @@ impl CombinatorHandler {
             match pl.run_validator(validator)? {
                 StepCompletion::Normal(_) | StepCompletion::NoIncrement(_) => {}
                 StepCompletion::WithWarnings(_, warnings) => combined_warnings.extend(warnings),
+                StepCompletion::WithPause(..) => {
+                    unreachable!("validators should not be able to pause the pipeline")
+                }
                 StepCompletion::WithExit => {
                     unimplemented!(
                         "combinator members requesting a pipeline exit is not implemented"
@@ -80,6 +83,7 @@ impl CombinatorHandler {
                 ret @ Ok(StepCompletion::Normal(_) | StepCompletion::NoIncrement(_)) => return ret,
                 ret @ Ok(StepCompletion::WithWarnings(_, _)) => return ret,
 
+                Ok(StepCompletion::WithPause(..)) => unreachable!("validators should not be able to pause the pipeline"),
                 Ok(StepCompletion::WithExit) => unreachable!("validators should not be able to kill the program via OkWithExit, only by errors"),
 
                 Err(_) =>  {},
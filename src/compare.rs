@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::cli::CompareArgs;
+use crate::outcome::Outcome;
+use crate::report::{RunReport, StepReport};
+
+// the percentiles compared, as named in LatencyReport
+const PERCENTILES: [&str; 3] = ["p50", "p95", "p99"];
+
+type StepKey<'a> = (&'a str, &'a str, &'a str);
+
+/// how far the candidate run may fall behind the baseline before compare fails. tolerances not
+/// given aren't checked
+#[derive(Clone, Debug, Default)]
+pub struct Tolerances {
+    // rise in any compared latency percentile, in percent of the baseline's
+    pub latency: Option<f64>,
+
+    // rise in error rate, in percentage points
+    pub error_rate: Option<f64>,
+
+    // drop in throughput, in percent of the baseline's
+    pub throughput: Option<f64>,
+}
+
+/// the steps of two run reports, matched up by situation, persona, and step name
+#[derive(Debug)]
+pub struct Comparison<'a> {
+    matched: Vec<(&'a StepReport, &'a StepReport)>,
+    only_baseline: Vec<&'a StepReport>,
+    only_candidate: Vec<&'a StepReport>,
+}
+
+impl<'a> Comparison<'a> {
+    pub fn new(baseline: &'a RunReport, candidate: &'a RunReport) -> Self {
+        let mut candidates: BTreeMap<StepKey, &StepReport> = candidate
+            .steps
+            .iter()
+            .map(|step| (key(step), step))
+            .collect();
+
+        let mut comparison = Self {
+            matched: Vec::new(),
+            only_baseline: Vec::new(),
+            only_candidate: Vec::new(),
+        };
+
+        let mut baselines: Vec<&StepReport> = baseline.steps.iter().collect();
+        baselines.sort_by_key(|step| key(step));
+
+        for step in baselines {
+            match candidates.remove(&key(step)) {
+                Some(other) => comparison.matched.push((step, other)),
+                None => comparison.only_baseline.push(step),
+            }
+        }
+
+        comparison.only_candidate = candidates.into_values().collect();
+
+        comparison
+    }
+
+    /// every way in which a matched step of the candidate fell further behind the baseline than
+    /// tolerated, described
+    pub fn regressions(&self, tolerances: &Tolerances) -> Vec<String> {
+        let mut regressions = Vec::new();
+
+        for (baseline, candidate) in &self.matched {
+            let what = format!(
+                "[{}] {} / {}",
+                baseline.situation, baseline.persona, baseline.step
+            );
+
+            if let Some(tolerance) = tolerances.throughput {
+                if let Some(change) = percent_change(baseline.throughput, candidate.throughput) {
+                    if -change > tolerance {
+                        regressions.push(format!(
+                            "{}: throughput dropped {:.1}% (tolerance {}%)",
+                            what, -change, tolerance
+                        ));
+                    }
+                }
+            }
+
+            if let Some(tolerance) = tolerances.error_rate {
+                let change = (candidate.error_rate() - baseline.error_rate()) * 100.0;
+                if change > tolerance {
+                    regressions.push(format!(
+                        "{}: error rate rose {:.2} percentage points (tolerance {})",
+                        what, change, tolerance
+                    ));
+                }
+            }
+
+            if let Some(tolerance) = tolerances.latency {
+                for percentile in PERCENTILES {
+                    let change = latency(baseline, percentile)
+                        .zip(latency(candidate, percentile))
+                        .and_then(|(base, cand)| percent_change(base, cand));
+
+                    if let Some(change) = change {
+                        if change > tolerance {
+                            regressions.push(format!(
+                                "{}: {} latency rose {:.1}% (tolerance {}%)",
+                                what, percentile, change, tolerance
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        regressions
+    }
+
+    /// a row for each matched step, grouped under its situation and persona as in the summary
+    /// table, showing the candidate's figures and how they changed from the baseline's. steps
+    /// found in only one of the reports are listed after
+    pub fn render(&self) -> String {
+        let mut rows: Vec<(String, Option<(&StepReport, &StepReport)>)> = Vec::new();
+        let mut previous: Option<(&str, &str)> = None;
+
+        for (baseline, candidate) in &self.matched {
+            if previous.map(|(prev, _)| prev) != Some(baseline.situation.as_str()) {
+                rows.push((baseline.situation.clone(), None));
+                previous = None;
+            }
+
+            if previous.map(|(_, prev)| prev) != Some(baseline.persona.as_str()) {
+                rows.push((format!("  {}", baseline.persona), None));
+            }
+
+            previous = Some((&baseline.situation, &baseline.persona));
+            rows.push((
+                format!("    {}", baseline.step),
+                Some((baseline, candidate)),
+            ));
+        }
+
+        let label_width = rows
+            .iter()
+            .map(|(label, _)| label.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let mut out = String::new();
+        let _ = write!(out, "{:<width$}", "", width = label_width);
+        for header in ["req/s", "err%"].iter().chain(&PERCENTILES) {
+            let _ = write!(out, " {:>8} {:>8}", header, "change");
+        }
+        let _ = writeln!(out);
+
+        for (label, steps) in rows {
+            let (baseline, candidate) = match steps {
+                Some(steps) => steps,
+                None => {
+                    let _ = writeln!(out, "{}", label);
+                    continue;
+                }
+            };
+
+            let _ = write!(out, "{:<width$}", label, width = label_width);
+            let _ = write!(
+                out,
+                " {:>8.1} {:>8}",
+                candidate.throughput,
+                format_change(
+                    percent_change(baseline.throughput, candidate.throughput),
+                    "%"
+                )
+            );
+
+            let _ = write!(
+                out,
+                " {:>8.2} {:>8}",
+                candidate.error_rate() * 100.0,
+                format_change(
+                    Some((candidate.error_rate() - baseline.error_rate()) * 100.0),
+                    "pp"
+                )
+            );
+
+            for percentile in PERCENTILES {
+                let base = latency(baseline, percentile);
+                let cand = latency(candidate, percentile);
+                let change = base
+                    .zip(cand)
+                    .and_then(|(base, cand)| percent_change(base, cand));
+
+                match cand {
+                    Some(cand) => {
+                        let _ = write!(out, " {:>8.1}", cand);
+                    }
+                    None => {
+                        let _ = write!(out, " {:>8}", "-");
+                    }
+                }
+                let _ = write!(out, " {:>8}", format_change(change, "%"));
+            }
+
+            let _ = writeln!(out);
+        }
+
+        for (steps, report) in [
+            (&self.only_baseline, "baseline"),
+            (&self.only_candidate, "candidate"),
+        ] {
+            for step in steps {
+                let _ = writeln!(
+                    out,
+                    "only in {}: [{}] {} / {}",
+                    report, step.situation, step.persona, step.step
+                );
+            }
+        }
+
+        out
+    }
+}
+
+/// loads both reports, prints their comparison, and fails if the candidate regressed beyond any
+/// of the tolerances given
+pub fn compare_reports(args: &CompareArgs) -> Outcome {
+    let mut reports = Vec::with_capacity(2);
+    for path in [&args.baseline, &args.candidate] {
+        match RunReport::load(path) {
+            Ok(report) => reports.push(report),
+            Err(err) => {
+                eprintln!(
+                    "[seatrial] could not read report {}: {}",
+                    path.display(),
+                    err
+                );
+                return Outcome::ConfigFailure;
+            }
+        }
+    }
+
+    let comparison = Comparison::new(&reports[0], &reports[1]);
+    print!("{}", comparison.render());
+
+    let regressions = comparison.regressions(&args.tolerances);
+    for regression in &regressions {
+        println!("regression: {}", regression);
+    }
+
+    if regressions.is_empty() {
+        Outcome::Success
+    } else {
+        Outcome::ThresholdFailure
+    }
+}
+
+fn key(step: &StepReport) -> StepKey<'_> {
+    (&step.situation, &step.persona, &step.step)
+}
+
+fn latency(step: &StepReport, percentile: &str) -> Option<f64> {
+    step.latency_ms.as_ref().map(|latency| match percentile {
+        "p50" => latency.p50,
+        "p95" => latency.p95,
+        "p99" => latency.p99,
+        _ => unreachable!(
+            "internal error: comparing unknown percentile {}",
+            percentile
+        ),
+    })
+}
+
+// there's no percentage of nothing to speak of
+fn percent_change(baseline: f64, candidate: f64) -> Option<f64> {
+    if baseline > 0.0 {
+        Some((candidate - baseline) / baseline * 100.0)
+    } else {
+        None
+    }
+}
+
+fn format_change(change: Option<f64>, unit: &str) -> String {
+    match change {
+        Some(change) => format!("{:+.1}{}", change, unit),
+        None => "-".into(),
+    }
+}
+
+#[cfg(test)]
+fn report(steps: &[(&str, u64, u64, f64, Option<f64>)]) -> RunReport {
+    use crate::report::LatencyReport;
+
+    RunReport {
+        seatrial_version: "0.0.0".into(),
+        seed: 1,
+        started_ms: 0,
+        elapsed_ms: 10_000.0,
+        steps: steps
+            .iter()
+            .map(|(step, count, errors, throughput, latency)| StepReport {
+                situation: "checkout".into(),
+                persona: "Buyer".into(),
+                step: String::from(*step),
+                count: *count,
+                errors: *errors,
+                throughput: *throughput,
+                latency_ms: latency.map(|latency| LatencyReport {
+                    min: latency,
+                    mean: latency,
+                    p50: latency,
+                    p90: latency,
+                    p95: latency,
+                    p99: latency,
+                    max: latency,
+                }),
+            })
+            .collect(),
+        dropped_arrivals: None,
+    }
+}
+
+#[test]
+fn test_matching() {
+    let baseline = report(&[
+        ("view cart", 100, 0, 10.0, Some(20.0)),
+        ("log in", 10, 0, 1.0, Some(5.0)),
+    ]);
+    let candidate = report(&[
+        ("view cart", 100, 2, 10.0, Some(30.0)),
+        ("pay", 10, 10, 1.0, None),
+    ]);
+
+    assert_eq!(
+        "                 req/s   change     err%   change      p50   change      p95   change      p99   change
+checkout
+  Buyer
+    view cart     10.0    +0.0%     2.00   +2.0pp     30.0   +50.0%     30.0   +50.0%     30.0   +50.0%
+only in baseline: [checkout] Buyer / log in
+only in candidate: [checkout] Buyer / pay
+",
+        Comparison::new(&baseline, &candidate).render()
+    );
+}
+
+#[test]
+fn test_regressions() {
+    let baseline = report(&[("view cart", 100, 1, 10.0, Some(20.0))]);
+    let candidate = report(&[("view cart", 100, 3, 8.0, Some(23.0))]);
+    let comparison = Comparison::new(&baseline, &candidate);
+
+    assert!(comparison.regressions(&Tolerances::default()).is_empty());
+
+    assert!(comparison
+        .regressions(&Tolerances {
+            latency: Some(20.0),
+            error_rate: Some(2.5),
+            throughput: Some(25.0),
+        })
+        .is_empty());
+
+    assert_eq!(
+        vec![
+            "[checkout] Buyer / view cart: throughput dropped 20.0% (tolerance 10%)",
+            "[checkout] Buyer / view cart: error rate rose 2.00 percentage points (tolerance 1)",
+            "[checkout] Buyer / view cart: p50 latency rose 15.0% (tolerance 10%)",
+            "[checkout] Buyer / view cart: p95 latency rose 15.0% (tolerance 10%)",
+            "[checkout] Buyer / view cart: p99 latency rose 15.0% (tolerance 10%)",
+        ],
+        comparison.regressions(&Tolerances {
+            latency: Some(10.0),
+            error_rate: Some(1.0),
+            throughput: Some(10.0),
+        })
+    );
+}
@@ -1,11 +1,14 @@
 use nanoserde::DeRon;
 
+use std::str::FromStr;
 use std::time::Duration;
 
 #[derive(Clone, Debug, DeRon)]
 pub enum ConfigDuration {
     Milliseconds(u64),
     Seconds(u64),
+    Minutes(u64),
+    Hours(u64),
 }
 
 impl From<&ConfigDuration> for Duration {
@@ -13,6 +16,37 @@ impl From<&ConfigDuration> for Duration {
         match src {
             ConfigDuration::Milliseconds(ms) => Self::from_millis(*ms),
             ConfigDuration::Seconds(ms) => Self::from_secs(*ms),
+            ConfigDuration::Minutes(mins) => Self::from_secs(*mins * 60),
+            ConfigDuration::Hours(hours) => Self::from_secs(*hours * 60 * 60),
+        }
+    }
+}
+
+/// parses the CLI shorthand for durations: an integer followed by one of the suffixes ms, s, m,
+/// or h. a bare integer is taken to be seconds
+impl FromStr for ConfigDuration {
+    type Err = String;
+
+    fn from_str(it: &str) -> Result<Self, Self::Err> {
+        let it = it.trim();
+        let split_at = it
+            .find(|chr: char| !chr.is_ascii_digit())
+            .unwrap_or(it.len());
+        let (count, unit) = it.split_at(split_at);
+
+        let count: u64 = count
+            .parse()
+            .map_err(|_| format!("expected a duration like 90s or 30m, got \"{}\"", it))?;
+
+        match unit {
+            "ms" => Ok(Self::Milliseconds(count)),
+            "" | "s" => Ok(Self::Seconds(count)),
+            "m" => Ok(Self::Minutes(count)),
+            "h" => Ok(Self::Hours(count)),
+            other => Err(format!(
+                "unknown duration unit \"{}\" (expected one of ms, s, m, h)",
+                other
+            )),
         }
     }
 }
@@ -32,3 +66,33 @@ fn test_milliseconds() {
         (&ConfigDuration::Milliseconds(100)).into()
     );
 }
+
+#[test]
+fn test_minutes_and_hours() {
+    assert_eq!(
+        Duration::from_secs(30 * 60),
+        (&ConfigDuration::Minutes(30)).into()
+    );
+    assert_eq!(
+        Duration::from_secs(2 * 60 * 60),
+        (&ConfigDuration::Hours(2)).into()
+    );
+}
+
+#[test]
+fn test_from_str() {
+    let parse = |it: &str| -> Duration { (&it.parse::<ConfigDuration>().unwrap()).into() };
+
+    assert_eq!(Duration::from_millis(250), parse("250ms"));
+    assert_eq!(Duration::from_secs(90), parse("90s"));
+    assert_eq!(Duration::from_secs(90), parse("90"));
+    assert_eq!(Duration::from_secs(30 * 60), parse("30m"));
+    assert_eq!(Duration::from_secs(60 * 60), parse("1h"));
+}
+
+#[test]
+fn test_from_str_rejects_garbage() {
+    assert!("".parse::<ConfigDuration>().is_err());
+    assert!("m".parse::<ConfigDuration>().is_err());
+    assert!("10 fortnights".parse::<ConfigDuration>().is_err());
+}
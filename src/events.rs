@@ -0,0 +1,185 @@
+use nanoserde::SerJson;
+
+use std::fs::File;
+use std::io::{BufWriter, Result as IOResult, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// what grunts report back to the main thread as the run goes
+// the overwhelming majority of events are steps, so there's nothing to be saved by boxing them
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum RunEvent {
+    Step(StepEvent),
+
+    // a grunt got going (once past its ramp-up, or at the start of its phase)
+    GruntStarted(GruntEvent),
+
+    // a grunt finished up, or was retired at the end of its phase
+    GruntStopped(GruntEvent),
+}
+
+#[derive(Clone, Debug)]
+pub struct GruntEvent {
+    pub situation: String,
+    pub persona: String,
+}
+
+/// everything worth knowing about a single pipeline step once it's done, sent by each grunt's
+/// pipeline to the main thread as the run goes
+#[derive(Clone, Debug)]
+pub struct StepEvent {
+    pub timestamp: SystemTime,
+    pub situation: String,
+    pub persona: String,
+    pub grunt: String,
+    pub step: usize,
+    pub name: String,
+
+    // the kind of action run, such as "Http" or "LuaFunction"
+    pub action: &'static str,
+
+    // for Http actions only: the method, and the URL sans query parameters
+    pub method: Option<&'static str>,
+    pub url: Option<String>,
+
+    // for Http actions which got a response (of any status) only
+    pub status_code: Option<u16>,
+    pub bytes: Option<usize>,
+
+    pub latency: Duration,
+    pub warnings: Vec<String>,
+
+    // the kind of StepError the step failed with, if it did, and its details, if it has any
+    pub error: Option<&'static str>,
+    pub error_message: Option<String>,
+}
+
+// the JSON shape of a StepEvent, as written to --events: the timestamp in whole milliseconds since
+// the Unix epoch, latency in fractional milliseconds, and optional fields left out when they don't
+// apply
+#[derive(SerJson)]
+struct StepEventRecord {
+    timestamp_ms: u64,
+    situation: String,
+    persona: String,
+    grunt: String,
+    step: usize,
+    name: String,
+    action: String,
+    method: Option<String>,
+    url: Option<String>,
+    status_code: Option<u16>,
+    bytes: Option<usize>,
+    latency_ms: f64,
+    warnings: Vec<String>,
+    error: Option<String>,
+    error_message: Option<String>,
+}
+
+impl StepEvent {
+    pub fn to_json(&self) -> String {
+        StepEventRecord {
+            timestamp_ms: self
+                .timestamp
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_millis() as u64),
+            situation: self.situation.clone(),
+            persona: self.persona.clone(),
+            grunt: self.grunt.clone(),
+            step: self.step,
+            name: self.name.clone(),
+            action: self.action.into(),
+            method: self.method.map(String::from),
+            url: self.url.clone(),
+            status_code: self.status_code,
+            bytes: self.bytes,
+            latency_ms: self.latency.as_secs_f64() * 1000.0,
+            warnings: self.warnings.clone(),
+            error: self.error.map(String::from),
+            error_message: self.error_message.clone(),
+        }
+        .serialize_json()
+    }
+}
+
+/// the file given with --events, to which each StepEvent is written as a line of JSON
+pub struct EventLog {
+    writer: BufWriter<File>,
+}
+
+impl EventLog {
+    pub fn create(path: &Path) -> IOResult<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    pub fn record(&mut self, event: &StepEvent) -> IOResult<()> {
+        writeln!(self.writer, "{}", event.to_json())
+    }
+
+    pub fn finish(mut self) -> IOResult<()> {
+        self.writer.flush()
+    }
+}
+
+// the fixtures every module's tests build their events from, overriding whichever fields matter
+// to them with struct update syntax
+#[cfg(test)]
+impl StepEvent {
+    /// a GET request by the first Buyer grunt of the checkout situation, started at the Unix
+    /// epoch, which got the given status, or failed outright lacking one
+    pub fn test_http(name: &str, status_code: Option<u16>, latency: Duration) -> Self {
+        Self {
+            timestamp: UNIX_EPOCH,
+            situation: "checkout".into(),
+            persona: "Buyer".into(),
+            grunt: "Buyer 0".into(),
+            step: 0,
+            name: name.into(),
+            action: "Http",
+            method: Some("GET"),
+            url: Some("http://localhost/cart".into()),
+            status_code,
+            bytes: Some(0),
+            latency,
+            warnings: vec![],
+            error: status_code.map_or(Some("http"), |_| None),
+            error_message: None,
+        }
+    }
+
+    /// as test_http, but a Validator step which passed
+    pub fn test_validator(name: &str) -> Self {
+        Self {
+            action: "Validator",
+            method: None,
+            url: None,
+            status_code: None,
+            bytes: None,
+            error: None,
+            ..Self::test_http(name, None, Duration::from_millis(1))
+        }
+    }
+}
+
+#[test]
+fn test_to_json() {
+    let event = StepEvent {
+        timestamp: UNIX_EPOCH + Duration::from_millis(1_650_000_000_123),
+        step: 2,
+        bytes: Some(512),
+        warnings: vec!["header \"X-Foo\" \"is\" missing".into()],
+        ..StepEvent::test_http("view cart", Some(200), Duration::from_micros(12_500))
+    };
+
+    let json = event.to_json();
+
+    assert!(!json.contains('\n'), "{}", json);
+    assert!(json.contains("\"timestamp_ms\":1650000000123"), "{}", json);
+    assert!(json.contains("\"latency_ms\":12.5"), "{}", json);
+    assert!(json.contains("\"status_code\":200"), "{}", json);
+    assert!(!json.contains("\"error\""), "{}", json);
+    assert!(json.contains("\\\"X-Foo\\\""), "{}", json);
+}
@@ -0,0 +1,767 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::events::{GruntEvent, RunEvent};
+use crate::grunt::Grunt;
+use crate::lua::LuaForPipeline;
+use crate::outcome::Outcome;
+use crate::pipeline::step_handler::{StepError, StepHandlerInitError};
+use crate::pipeline::{Pipeline, PipelineStepResult};
+use crate::shutdown::{StopCondition, StopReason};
+use crate::situation::Situation;
+
+#[cfg(test)]
+use crate::phase::ActiveWindow;
+#[cfg(test)]
+use crate::targets::{parse_base_url, Targets};
+#[cfg(test)]
+use std::collections::HashMap;
+#[cfg(test)]
+use std::sync::mpsc::{channel, sync_channel};
+
+// how often an idle worker whose grunts are all waiting on open-model arrivals checks for new
+// ones. arrivals are handed out through a channel shared by every worker, so there's no single
+// place to block on them
+const ARRIVAL_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+// the longest an idle worker sleeps in one go before checking whether it's been asked to stop
+const IDLE_SLICE: Duration = Duration::from_millis(100);
+
+/// a grunt to be run by a worker, along with the situation it belongs to and, for open-model
+/// situations, where its arrivals come from
+#[derive(Clone)]
+pub struct Assignment {
+    pub situation: &'static Situation,
+    pub grunt: &'static Grunt,
+    pub arrivals: Option<Arc<Mutex<Receiver<()>>>>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum TaskState {
+    // waiting out the grunt's ramp-up (or for its next phase), until the given instant
+    Starting(Instant),
+
+    // walking the persona, one step per turn
+    Running,
+
+    // taking a breather at the behest of a Sleep, RandomSleep, or Pace, until the given instant
+    Pausing(Instant),
+
+    // open model only: idle between passes until an arrival is handed to it
+    AwaitingArrival,
+
+    Done,
+}
+
+// everything a worker tracks about one of its grunts between turns. a grunt never moves between
+// workers, so its Lua context (which can't be sent across threads) stays put
+struct GruntTask<'lua> {
+    grunt: &'static Grunt,
+    pipeline: Pipeline<'lua, 'static, 'static>,
+    stop: StopCondition,
+    arrivals: Option<Arc<Mutex<Receiver<()>>>>,
+    state: TaskState,
+    outcome: Outcome,
+
+    // when the run started, and which of the grunt's active windows it's in (or waiting on)
+    started: Instant,
+    window: usize,
+
+    // whether the grunt is past its ramp-up (or the start of its phase) and not yet done, and
+    // where to report that changing
+    active: bool,
+    events: Sender<RunEvent>,
+}
+
+impl<'lua> GruntTask<'lua> {
+    fn new(
+        grunt: &'static Grunt,
+        pipeline: Pipeline<'lua, 'static, 'static>,
+        stop: StopCondition,
+        arrivals: Option<Arc<Mutex<Receiver<()>>>>,
+        started: Instant,
+        events: Sender<RunEvent>,
+    ) -> Self {
+        Self {
+            grunt,
+            pipeline,
+            stop,
+            arrivals,
+            // with phases, a grunt can find itself with no time at all in which to be active
+            state: match grunt.windows.first() {
+                Some(window) => TaskState::Starting(started + window.start),
+                None => TaskState::Done,
+            },
+            outcome: Outcome::Success,
+            started,
+            window: 0,
+            active: false,
+            events,
+        }
+    }
+
+    // gives the grunt one turn: one pipeline step, or one check on whatever it's waiting for.
+    // returns whether anything happened, so the worker knows when it's run out of things to do
+    fn take_turn(&mut self, now: Instant) -> bool {
+        match self.state {
+            TaskState::Done => false,
+
+            TaskState::Starting(at) => {
+                if let Some(reason) = self.stop.check() {
+                    report_stop(self.grunt, reason);
+                    self.finish();
+                    return true;
+                }
+
+                if now < at {
+                    return false;
+                }
+
+                self.state = match self.arrivals {
+                    Some(_) => TaskState::AwaitingArrival,
+                    None => TaskState::Running,
+                };
+                self.set_active(true);
+                true
+            }
+
+            // a grunt whose phase has ended is retired between steps, mid-pass or not, such that
+            // the phase boundary holds even for personas whose passes take a while
+            TaskState::AwaitingArrival | TaskState::Running | TaskState::Pausing(_)
+                if self.window_ended(now) =>
+            {
+                self.retire();
+                true
+            }
+
+            // each arrival is its own simulated user, so one failing or exiting doesn't retire
+            // the grunt, but running out of time (or out of arrivals, which the scheduler signals
+            // by hanging up) does
+            TaskState::AwaitingArrival => {
+                let arrival = self
+                    .arrivals
+                    .as_ref()
+                    .expect("internal error: awaiting arrivals in a closed-model situation")
+                    .lock()
+                    .expect("internal error: arrivals lock poisoned")
+                    .try_recv();
+
+                match arrival {
+                    Ok(()) => {
+                        self.pipeline.rewind();
+                        self.state = TaskState::Running;
+                        true
+                    }
+                    Err(TryRecvError::Empty) => false,
+                    Err(TryRecvError::Disconnected) => {
+                        self.finish();
+                        true
+                    }
+                }
+            }
+
+            TaskState::Pausing(until) => {
+                if let Some(reason) = self.stop.check() {
+                    report_stop(self.grunt, reason);
+                    self.end_pass(PassEnd::Stopped);
+                    return true;
+                }
+
+                if now < until {
+                    return false;
+                }
+
+                self.state = TaskState::Running;
+                true
+            }
+
+            TaskState::Running => {
+                match run_step(self.grunt, &mut self.pipeline, &self.stop) {
+                    StepEnd::Continue => {}
+                    StepEnd::Pause(pause) => self.state = TaskState::Pausing(now + pause),
+                    StepEnd::PassEnded(end) => self.end_pass(end),
+                }
+                true
+            }
+        }
+    }
+
+    fn end_pass(&mut self, end: PassEnd) {
+        if let PassEnd::Failed(failure) = end {
+            self.outcome = self.outcome.worst(failure);
+        }
+
+        match (&self.arrivals, end) {
+            (Some(_), PassEnd::Stopped) => self.finish(),
+            (Some(_), _) => self.state = TaskState::AwaitingArrival,
+
+            // closed model: walk the persona as fast as responses allow, starting over from the
+            // top for as long as there's a run duration in place and time left on it, whether
+            // the pass fell off the end of the sequence or was ended by a GoTo's max_times
+            (None, PassEnd::Exhausted | PassEnd::Exited)
+                if self.stop.has_deadline() && !self.grunt.persona.sequence.is_empty() =>
+            {
+                self.pipeline.rewind()
+            }
+            (None, _) => self.finish(),
+        }
+    }
+
+    fn window_ended(&self, now: Instant) -> bool {
+        self.grunt.windows[self.window]
+            .end
+            .map_or(false, |end| now >= self.started + end)
+    }
+
+    // moves on to the grunt's next active window, if it has one
+    fn retire(&mut self) {
+        eprintln!("[{}] phase ended, retiring", self.grunt.name);
+        self.set_active(false);
+
+        self.window += 1;
+        match self.grunt.windows.get(self.window) {
+            Some(window) => {
+                self.pipeline.rewind();
+                self.state = TaskState::Starting(self.started + window.start);
+            }
+            None => self.finish(),
+        }
+    }
+
+    fn finish(&mut self) {
+        grunt_exit(self.grunt);
+        self.set_active(false);
+        self.state = TaskState::Done;
+    }
+
+    fn set_active(&mut self, active: bool) {
+        if self.active == active {
+            return;
+        }
+
+        self.active = active;
+        let event = GruntEvent {
+            situation: self.grunt.situation.clone(),
+            persona: self.grunt.base_name.clone(),
+        };
+
+        // the receiving end only hangs up once the run is over, by which point nobody's listening
+        let _ = self.events.send(if active {
+            RunEvent::GruntStarted(event)
+        } else {
+            RunEvent::GruntStopped(event)
+        });
+    }
+
+    // when this task next has something to do, if it's waiting on anything at all
+    fn wake_at(&self, now: Instant) -> Option<Instant> {
+        match self.state {
+            TaskState::Starting(at) | TaskState::Pausing(at) => Some(at),
+            TaskState::AwaitingArrival => Some(now + ARRIVAL_POLL_INTERVAL),
+            TaskState::Running => Some(now),
+            TaskState::Done => None,
+        }
+    }
+}
+
+/// runs every assigned grunt to completion on the current thread, interleaving them a step at a
+/// time, and returns the worst outcome among them. blocking work (an HTTP request, a Lua
+/// function) holds up the worker's other grunts, but waiting (on a ramp-up, or for an arrival)
+/// doesn't
+pub fn worker(
+    barrier: Arc<Barrier>,
+    assignments: Vec<Assignment>,
+    interrupted: Arc<AtomicBool>,
+    events: Sender<RunEvent>,
+) -> Result<Outcome, StepHandlerInitError> {
+    let luas: Vec<Option<LuaForPipeline>> = assignments
+        .iter()
+        .map(|assignment| load_lua(assignment.situation, assignment.grunt))
+        .collect();
+
+    // even a worker whose grunts failed to load their Lua contexts must show up at the starting
+    // line, lest everyone else wait for it forever
+    barrier.wait();
+
+    let started = Instant::now();
+    let mut outcome = Outcome::Success;
+    let mut tasks: Vec<GruntTask> = Vec::with_capacity(assignments.len());
+
+    for (assignment, lua) in assignments.into_iter().zip(&luas) {
+        let lua = match lua {
+            Some(lua) => lua,
+            None => {
+                outcome = outcome.worst(Outcome::LuaFailure);
+                continue;
+            }
+        };
+
+        tasks.push(GruntTask::new(
+            assignment.grunt,
+            Pipeline::new(
+                assignment.grunt,
+                &assignment.situation.targets,
+                Some(lua),
+                false,
+                Some(events.clone()),
+            )?,
+            StopCondition::new(
+                assignment
+                    .situation
+                    .duration
+                    .map(|duration| started + duration),
+                interrupted.clone(),
+            ),
+            assignment.arrivals,
+            started,
+            events.clone(),
+        ));
+    }
+
+    // round-robin, such that no grunt waits on more than one step from each of the others before
+    // getting another turn
+    let mut cursor = 0;
+
+    loop {
+        let now = Instant::now();
+        let took_turn = (0..tasks.len()).any(|offset| {
+            let idx = (cursor + offset) % tasks.len();
+            let took_turn = tasks[idx].take_turn(now);
+            if took_turn {
+                cursor = idx + 1;
+            }
+            took_turn
+        });
+
+        if took_turn {
+            continue;
+        }
+
+        match tasks.iter().filter_map(|task| task.wake_at(now)).min() {
+            None => break,
+            Some(wake_at) => thread::sleep(wake_at.saturating_duration_since(now).min(IDLE_SLICE)),
+        }
+    }
+
+    Ok(tasks
+        .iter()
+        .fold(outcome, |outcome, task| outcome.worst(task.outcome)))
+}
+
+// reports (and swallows) Lua load errors, leaving the caller to decide what a grunt without a Lua
+// context should do
+pub fn load_lua(situation: &Situation, grunt: &Grunt) -> Option<LuaForPipeline> {
+    if situation.lua_file.is_none() {
+        unimplemented!("situations without 'lua_file' are not currently supported");
+    }
+
+    // lua_file.unwrap() is guarded above
+    match LuaForPipeline::new(situation.lua_file.as_ref().unwrap(), grunt.seed) {
+        Ok(lua) => Some(lua),
+        Err(err) => {
+            eprintln!("[{}] aborting due to lua error", grunt.name);
+            eprintln!("[{}] err was: {}", grunt.name, err);
+            None
+        }
+    }
+}
+
+/// how a single pass through a grunt's pipeline came to an end
+#[derive(Debug, Eq, PartialEq)]
+pub enum PassEnd {
+    // the pipeline fell off the end of its sequence
+    Exhausted,
+
+    // the pipeline asked to exit (for example, a GoTo ran out of max_times)
+    Exited,
+
+    // a step failed, and the details have already been reported
+    Failed(Outcome),
+
+    // the situation's duration elapsed, or the run was interrupted, between steps
+    Stopped,
+}
+
+// what a grunt should do after a single pipeline step
+enum StepEnd {
+    Continue,
+    Pause(Duration),
+    PassEnded(PassEnd),
+}
+
+/// walks a grunt's pipeline from wherever it currently is to the end of a pass, for use where
+/// there's nothing else to interleave with (and thus pauses can simply block the thread)
+pub fn run_pass(grunt: &Grunt, pipeline: &mut Pipeline, stop: &StopCondition) -> PassEnd {
+    loop {
+        match run_step(grunt, pipeline, stop) {
+            StepEnd::Continue => {}
+            StepEnd::Pause(pause) => {
+                if let Some(reason) = stop.sleep(pause) {
+                    report_stop(grunt, reason);
+                    return PassEnd::Stopped;
+                }
+            }
+            StepEnd::PassEnded(end) => return end,
+        }
+    }
+}
+
+fn run_step(grunt: &Grunt, pipeline: &mut Pipeline, stop: &StopCondition) -> StepEnd {
+    if let Some(reason) = stop.check() {
+        report_stop(grunt, reason);
+        return StepEnd::PassEnded(PassEnd::Stopped);
+    }
+
+    // errors come back once the pipeline has moved on, so hang on to which step this was
+    let step = pipeline.step_index();
+
+    match pipeline.next() {
+        None => StepEnd::PassEnded(PassEnd::Exhausted),
+
+        Some(Ok(PipelineStepResult::Ok)) => StepEnd::Continue,
+
+        Some(Ok(PipelineStepResult::OkWithPause(pause))) => StepEnd::Pause(pause),
+
+        Some(Ok(PipelineStepResult::OkWithExit)) => StepEnd::PassEnded(PassEnd::Exited),
+
+        Some(Err(err)) => {
+            let outcome = Outcome::from(&err);
+            process_step_error(grunt, &grunt.persona.step_names[step], err);
+            StepEnd::PassEnded(PassEnd::Failed(outcome))
+        }
+    }
+}
+
+fn report_stop(grunt: &Grunt, reason: StopReason) {
+    match reason {
+        StopReason::Interrupted => eprintln!("[{}] run interrupted, wrapping up", grunt.name),
+        StopReason::OutOfTime => eprintln!("[{}] run duration elapsed, wrapping up", grunt.name),
+    }
+}
+
+fn grunt_exit(grunt: &Grunt) {
+    eprintln!("[{}] reached end of pipeline, goodbye!", grunt.name);
+}
+
+fn process_step_error(grunt: &Grunt, step: &str, err: StepError) {
+    match err {
+        StepError::Unclassified => {
+            eprintln!(
+                "[{}] aborting due to unclassified error in pipeline",
+                grunt.name
+            );
+            eprintln!(
+                "[{}] this is an error in seatrial - TODO fix this",
+                grunt.name
+            );
+            eprintln!("[{}] step was: {}", grunt.name, step);
+        }
+
+        StepError::Validation(err) => {
+            eprintln!(
+                "[{}] aborting due to validation error in pipeline",
+                grunt.name
+            );
+            eprintln!("[{}] err was: {}", grunt.name, err);
+            eprintln!("[{}] step was: {}", grunt.name, step);
+        }
+
+        // TODO: more details - we're just not plumbing the details around
+        StepError::ValidationSucceededUnexpectedly => {
+            eprintln!(
+                "[{}] aborting because a validation succeeded where we expected a failure",
+                grunt.name
+            );
+            eprintln!(
+                "[{}] this is an error in seatrial - TODO fix this",
+                grunt.name
+            );
+            eprintln!("[{}] step was: {}", grunt.name, step);
+        }
+
+        StepError::InvalidActionInContext => {
+            eprintln!(
+                "[{}] aborting due to invalid action definition in the given context",
+                grunt.name
+            );
+            eprintln!(
+                "[{}] seatrial lint should have caught this; that it didn't is an error in seatrial",
+                grunt.name
+            );
+            eprintln!("[{}] step was: {}", grunt.name, step);
+        }
+
+        StepError::IO(err) => {
+            eprintln!("[{}] aborting due to internal IO error", grunt.name);
+            eprintln!("[{}] err was: {}", grunt.name, err);
+            eprintln!("[{}] step was: {}", grunt.name, step);
+        }
+
+        StepError::LuaException(err) => {
+            eprintln!("[{}] aborting due to lua error", grunt.name);
+            eprintln!("[{}] err was: {}", grunt.name, err);
+            eprintln!("[{}] step was: {}", grunt.name, step);
+        }
+
+        StepError::UrlParsing(err) => {
+            eprintln!("[{}] aborting due to url parsing error", grunt.name);
+            eprintln!("[{}] err was: {}", grunt.name, err);
+            eprintln!("[{}] step was: {}", grunt.name, step);
+        }
+
+        StepError::Http(err) => {
+            eprintln!("[{}] aborting due to http error", grunt.name);
+            eprintln!("[{}] err was: {}", grunt.name, err);
+            eprintln!("[{}] step was: {}", grunt.name, step);
+        }
+
+        StepError::RefuseToStringifyComplexLuaValue => {
+            eprintln!(
+                "[{}] aborting attempt to stringify complex lua value",
+                grunt.name
+            );
+            eprintln!("[{}] step was: {}", grunt.name, step);
+        }
+
+        StepError::RefuseToStringifyNonExistantValue => {
+            eprintln!(
+                "[{}] aborting attempt to stringify non-existent (probably nil) lua value",
+                grunt.name
+            );
+            eprintln!("[{}] step was: {}", grunt.name, step);
+        }
+
+        // TODO: FIXME this messaging is extremely hard to grok, I'd be pounding my head
+        // into the keyboard screaming obscenities if a tool offered me this as the sole
+        // debug output
+        StepError::RequestedLuaValueWhereNoneExists => {
+            eprintln!(
+                "[{}] aborting attempt to pass non-existent value to lua context",
+                grunt.name
+            );
+            eprintln!("[{}] step was: {}", grunt.name, step);
+        }
+
+        StepError::LuaNotInstantiated => {
+            eprintln!(
+                "[{}] aborting attempt to use lua when it is not instantiated",
+                grunt.name
+            );
+            eprintln!("[{}] step was: {}", grunt.name, step);
+        }
+    }
+}
+
+#[cfg(test)]
+fn grunt_task(
+    grunt: Grunt,
+    duration: Option<Duration>,
+) -> (GruntTask<'static>, Receiver<RunEvent>) {
+    let grunt: &'static Grunt = Box::leak(Box::new(grunt));
+    let targets: &'static Targets = Box::leak(Box::new(Targets {
+        base_url: parse_base_url("http://localhost").unwrap(),
+        named: HashMap::new(),
+    }));
+    let (events, received) = channel();
+    let started = Instant::now();
+
+    let task = GruntTask::new(
+        grunt,
+        Pipeline::new(grunt, targets, None, false, Some(events.clone())).unwrap(),
+        StopCondition::new(duration.map(|duration| started + duration), Arc::default()),
+        None,
+        started,
+        events,
+    );
+
+    (task, received)
+}
+
+#[test]
+fn test_passes_start_over_for_the_duration() {
+    for sequence in [
+        // passes end by falling off the end of the sequence
+        "ControlFlow(Sleep(Milliseconds(0)))",
+        // passes end by exhausting a GoTo
+        "ControlFlow(Sleep(Milliseconds(0))), ControlFlow(GoTo(index: 0, max_times: 1))",
+    ] {
+        let (mut task, _events) = grunt_task(Grunt::test(sequence), Some(Duration::from_secs(60)));
+        let now = Instant::now();
+        for _ in 0..20 {
+            assert!(task.take_turn(now), "{}", sequence);
+        }
+        assert_ne!(TaskState::Done, task.state, "{}", sequence);
+        assert_eq!(Outcome::Success, task.outcome);
+
+        let (mut task, _events) = grunt_task(Grunt::test(sequence), None);
+        let now = Instant::now();
+        for _ in 0..20 {
+            task.take_turn(now);
+        }
+        assert_eq!(TaskState::Done, task.state, "{}", sequence);
+    }
+}
+
+#[test]
+fn test_duration_elapsing_ends_the_run() {
+    let (mut task, events) = grunt_task(
+        Grunt::test("ControlFlow(Sleep(Milliseconds(0)))"),
+        Some(Duration::ZERO),
+    );
+
+    assert!(task.take_turn(Instant::now()));
+    assert_eq!(TaskState::Done, task.state);
+    assert!(!task.take_turn(Instant::now()));
+
+    // it never got going, so never started as far as anyone else is concerned
+    assert!(events.try_recv().is_err());
+}
+
+#[cfg(test)]
+fn grunt_events(events: &Receiver<RunEvent>) -> Vec<&'static str> {
+    events
+        .try_iter()
+        .filter_map(|event| match event {
+            RunEvent::GruntStarted(_) => Some("started"),
+            RunEvent::GruntStopped(_) => Some("stopped"),
+            RunEvent::Step(_) => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_grunt_waits_out_its_ramp_up() {
+    let mut grunt = Grunt::test("ControlFlow(Sleep(Milliseconds(0)))");
+    grunt.windows[0].start = Duration::from_secs(10);
+    let (mut task, events) = grunt_task(grunt, None);
+    let started = task.started;
+
+    assert_eq!(
+        TaskState::Starting(started + Duration::from_secs(10)),
+        task.state
+    );
+    assert!(!task.take_turn(started + Duration::from_secs(9)));
+    assert!(grunt_events(&events).is_empty());
+
+    assert!(task.take_turn(started + Duration::from_secs(10)));
+    assert_eq!(TaskState::Running, task.state);
+    assert_eq!(vec!["started"], grunt_events(&events));
+}
+
+#[test]
+fn test_pauses_hold_up_only_their_own_grunt() {
+    let (mut task, events) = grunt_task(
+        Grunt::test("ControlFlow(Sleep(Milliseconds(50))), ControlFlow(Sleep(Milliseconds(0)))"),
+        None,
+    );
+    let now = Instant::now();
+
+    assert!(task.take_turn(now));
+    assert!(task.take_turn(now));
+    let until = now + Duration::from_millis(50);
+    assert_eq!(TaskState::Pausing(until), task.state);
+
+    // a pausing task reports that it had nothing to do, rather than blocking its worker
+    assert!(!task.take_turn(now + Duration::from_millis(49)));
+    assert_eq!(Some(until), task.wake_at(now));
+
+    assert!(task.take_turn(until));
+    assert_eq!(TaskState::Running, task.state);
+
+    // through the second pause, then off the end of the sequence without a duration
+    assert!(task.take_turn(until));
+    assert!(task.take_turn(until));
+    assert!(task.take_turn(until));
+    assert_eq!(TaskState::Done, task.state);
+    assert_eq!(None, task.wake_at(until));
+    assert_eq!(vec!["started", "stopped"], grunt_events(&events));
+}
+
+#[test]
+fn test_open_model_grunts_await_arrivals() {
+    let (mut task, events) = grunt_task(Grunt::test("ControlFlow(Sleep(Milliseconds(0)))"), None);
+    let (tickets, arrivals) = sync_channel::<()>(1);
+    task.arrivals = Some(Arc::new(Mutex::new(arrivals)));
+    let now = Instant::now();
+
+    assert!(task.take_turn(now));
+    assert_eq!(TaskState::AwaitingArrival, task.state);
+    assert!(!task.take_turn(now));
+
+    tickets.send(()).unwrap();
+    assert!(task.take_turn(now));
+    assert_eq!(TaskState::Running, task.state);
+
+    // the pause, then off the end of the sequence and back to waiting for the next arrival
+    assert!(task.take_turn(now));
+    assert!(task.take_turn(now));
+    assert!(task.take_turn(now));
+    assert_eq!(TaskState::AwaitingArrival, task.state);
+
+    // a failed pass is one unlucky arrival, not the end of the grunt
+    task.end_pass(PassEnd::Failed(Outcome::ValidationFailure));
+    assert_eq!(TaskState::AwaitingArrival, task.state);
+    assert_eq!(Outcome::ValidationFailure, task.outcome);
+
+    // the scheduler hanging up means there are no more arrivals to be had
+    drop(tickets);
+    assert!(task.take_turn(now));
+    assert_eq!(TaskState::Done, task.state);
+    assert_eq!(vec!["started", "stopped"], grunt_events(&events));
+}
+
+#[test]
+fn test_phase_end_retires_the_grunt_until_its_next_window() {
+    let mut grunt = Grunt::test("ControlFlow(Sleep(Milliseconds(0)))");
+    grunt.windows = vec![
+        ActiveWindow {
+            start: Duration::ZERO,
+            end: Some(Duration::from_secs(1)),
+        },
+        ActiveWindow {
+            start: Duration::from_secs(5),
+            end: Some(Duration::from_secs(6)),
+        },
+    ];
+    let (mut task, events) = grunt_task(grunt, Some(Duration::from_secs(60)));
+    let started = task.started;
+
+    assert!(task.take_turn(started));
+    assert!(task.take_turn(started));
+    assert!(matches!(task.state, TaskState::Pausing(_)));
+
+    // retired mid-pass, pause and all
+    assert!(task.take_turn(started + Duration::from_secs(1)));
+    assert_eq!(
+        TaskState::Starting(started + Duration::from_secs(5)),
+        task.state
+    );
+    assert!(!task.take_turn(started + Duration::from_secs(4)));
+
+    assert!(task.take_turn(started + Duration::from_secs(5)));
+    assert_eq!(TaskState::Running, task.state);
+
+    assert!(task.take_turn(started + Duration::from_secs(6)));
+    assert_eq!(TaskState::Done, task.state);
+    assert_eq!(
+        vec!["started", "stopped", "started", "stopped"],
+        grunt_events(&events)
+    );
+}
+
+#[test]
+fn test_failed_pass_ends_a_closed_model_grunt() {
+    let (mut task, _events) = grunt_task(
+        Grunt::test("ControlFlow(Sleep(Milliseconds(0)))"),
+        Some(Duration::from_secs(60)),
+    );
+    let now = Instant::now();
+
+    assert!(task.take_turn(now));
+    task.end_pass(PassEnd::Failed(Outcome::TransportFailure));
+    assert_eq!(TaskState::Done, task.state);
+    assert_eq!(Outcome::TransportFailure, task.outcome);
+}
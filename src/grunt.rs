@@ -5,59 +5,88 @@ use std::fmt::Display;
 #[cfg(test)]
 use crate::config_duration::ConfigDuration;
 use crate::persona::{Persona, PersonaSpec};
-use crate::situation::{SituationParseErr, SituationParseErrKind};
+use crate::phase::ActiveWindow;
+use crate::ramp_up::RampUp;
+use crate::seed::derive_seed;
+#[cfg(test)]
+use std::time::Duration;
 
 // build out of a GruntSpec during Situation construction
 #[derive(Clone, Debug)]
 pub struct Grunt {
     pub name: String,
     pub persona: Persona,
+
+    // the name of the situation this grunt belongs to, and the name shared by every copy of its
+    // spec (its persona, as far as reporting is concerned)
+    pub situation: String,
+    pub base_name: String,
+
+    // when, relative to the start of the run, this grunt should be walking its persona. usually
+    // one window, starting once any ramp-up delay has passed, but situations with phases may
+    // retire a grunt and bring it back later
+    pub windows: Vec<ActiveWindow>,
+
+    // what this grunt's Lua math.random is seeded with
+    pub seed: u64,
 }
 
 impl Grunt {
-    pub fn from_spec_with_multiplier(
+    pub fn from_spec(
         spec: &GruntSpec,
-        multiplier: usize,
-    ) -> Result<Vec<Self>, SituationParseErr> {
-        let num_grunts = spec.real_count() * multiplier;
-        if num_grunts < 1 {
-            return Err(SituationParseErr {
-                kind: SituationParseErrKind::Semantics {
-                    message: "if provided, grunt count must be >=1".into(),
-                    location: "unknown".into(), // this gets replaced upstream
-                },
-            });
-        }
+        copy: usize,
+        windows: Vec<ActiveWindow>,
+        situation: &str,
+        situation_seed: u64,
+    ) -> Self {
+        let name = spec.formatted_name(copy);
 
-        let mut grunts: Vec<Self> = Vec::with_capacity(num_grunts);
-        for slot in 0..num_grunts {
-            grunts.push(Grunt {
-                name: spec.formatted_name(slot),
-                persona: (&spec.persona).into(),
-            });
+        Self {
+            seed: derive_seed(situation_seed, &name),
+            name,
+            situation: situation.into(),
+            base_name: spec.display_name(),
+            persona: spec.persona().into(),
+            windows,
         }
-
-        Ok(grunts)
     }
 }
 
 #[derive(Clone, Debug, DeRon)]
 pub struct GruntSpec {
     pub base_name: Option<String>,
-    pub persona: PersonaSpec,
+
+    // exactly one of these is given in a situation file; by the time SituationSpec::load is done,
+    // a persona_name has been resolved into a persona from the situation's includes
+    pub persona: Option<PersonaSpec>,
+    pub persona_name: Option<String>,
+
     pub count: Option<usize>,
+    pub ramp_up: Option<RampUp>,
 }
 
 impl GruntSpec {
+    pub fn persona(&self) -> &PersonaSpec {
+        self.persona
+            .as_ref()
+            .expect("internal error: grunt persona was not resolved when its situation was loaded")
+    }
+
     pub fn formatted_name(&self, uniqueness: impl Display) -> String {
-        format!(
-            "{} {}",
-            self.base_name.clone().unwrap_or_else(|| format!(
-                "Grunt<taking {} actions>",
-                self.persona.sequence.len()
-            )),
-            uniqueness,
-        )
+        format!("{} {}", self.display_name(), uniqueness)
+    }
+
+    // the name shared by all grunts spawned from this spec, sans uniqueness suffix
+    pub fn display_name(&self) -> String {
+        self.base_name
+            .clone()
+            .unwrap_or_else(|| match &self.persona {
+                Some(persona) => format!("Grunt<taking {} actions>", persona.sequence.len()),
+                None => format!(
+                    "Grunt<{}>",
+                    self.persona_name.as_deref().unwrap_or("unknown")
+                ),
+            })
     }
 
     pub fn real_count(&self) -> usize {
@@ -65,16 +94,42 @@ impl GruntSpec {
     }
 }
 
+// the fixture the tests of modules walking grunts' pipelines build their grunts from
+#[cfg(test)]
+impl Grunt {
+    /// the first Buyer grunt of the checkout situation, walking the given sequence (in RON) from
+    /// the start of the run for as long as it likes
+    pub fn test(sequence: &str) -> Self {
+        let persona: PersonaSpec =
+            DeRon::deserialize_ron(&format!("(timeout: Seconds(5), sequence: [{}])", sequence))
+                .unwrap();
+
+        Self {
+            name: "Buyer 0".into(),
+            persona: (&persona).into(),
+            situation: "checkout".into(),
+            base_name: "Buyer".into(),
+            windows: vec![ActiveWindow {
+                start: Duration::ZERO,
+                end: None,
+            }],
+            seed: 0,
+        }
+    }
+}
+
 #[test]
 fn test_formatted_name() {
     let spec = GruntSpec {
         base_name: Some("Jimbo Gruntseph".into()),
-        persona: PersonaSpec {
+        persona: Some(PersonaSpec {
             headers: None,
             sequence: vec![],
             timeout: ConfigDuration::Seconds(30),
-        },
+        }),
+        persona_name: None,
         count: None,
+        ramp_up: None,
     };
 
     assert_eq!("Jimbo Gruntseph 1", spec.formatted_name(1));
@@ -84,12 +139,14 @@ fn test_formatted_name() {
 fn test_formatted_name_no_base() {
     let spec = GruntSpec {
         base_name: None,
-        persona: PersonaSpec {
+        persona: Some(PersonaSpec {
             headers: None,
             sequence: vec![],
             timeout: ConfigDuration::Seconds(30),
-        },
+        }),
+        persona_name: None,
         count: None,
+        ramp_up: None,
     };
 
     assert_eq!("Grunt<taking 0 actions> 1", spec.formatted_name(1));
@@ -99,12 +156,14 @@ fn test_formatted_name_no_base() {
 fn test_real_count() {
     let spec = GruntSpec {
         base_name: None,
-        persona: PersonaSpec {
+        persona: Some(PersonaSpec {
             headers: None,
             sequence: vec![],
             timeout: ConfigDuration::Seconds(30),
-        },
+        }),
+        persona_name: None,
         count: None,
+        ramp_up: None,
     };
 
     assert_eq!(1, spec.real_count());
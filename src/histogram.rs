@@ -0,0 +1,210 @@
+use std::time::Duration;
+
+// values below this many microseconds get a bucket each; above, each power of two is split into
+// half this many buckets, keeping every bucket within ~1.6% of the values it holds
+const LINEAR_BUCKETS: u64 = 128;
+const SUB_BUCKET_BITS: u32 = 6;
+
+/// a log-linear histogram of latencies at microsecond resolution, in the spirit of HdrHistogram:
+/// cheap to record into, a fixed (and small) size no matter how many values it holds, and
+/// mergeable, such that histograms kept per request can be summed up per grunt or situation
+#[derive(Clone, Debug, Default)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    count: u64,
+    sum_micros: u128,
+    min_micros: u64,
+    max_micros: u64,
+}
+
+impl Histogram {
+    pub fn record(&mut self, value: Duration) {
+        let micros = u64::try_from(value.as_micros()).unwrap_or(u64::MAX);
+
+        let idx = bucket_index(micros);
+        if idx >= self.counts.len() {
+            self.counts.resize(idx + 1, 0);
+        }
+        self.counts[idx] += 1;
+
+        if self.count == 0 || micros < self.min_micros {
+            self.min_micros = micros;
+        }
+        self.max_micros = self.max_micros.max(micros);
+        self.sum_micros += u128::from(micros);
+        self.count += 1;
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        if other.count == 0 {
+            return;
+        }
+
+        if other.counts.len() > self.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (mine, theirs) in self.counts.iter_mut().zip(&other.counts) {
+            *mine += theirs;
+        }
+
+        if self.count == 0 || other.min_micros < self.min_micros {
+            self.min_micros = other.min_micros;
+        }
+        self.max_micros = self.max_micros.max(other.max_micros);
+        self.sum_micros += other.sum_micros;
+        self.count += other.count;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> Duration {
+        Duration::from_micros(self.min_micros)
+    }
+
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.max_micros)
+    }
+
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+
+        Duration::from_micros((self.sum_micros / u128::from(self.count)) as u64)
+    }
+
+    /// the value below which the given percentage (0 to 100) of recorded values fall, give or
+    /// take the width of the bucket it lands in. always within [min, max]
+    pub fn percentile(&self, percent: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+
+        let rank = ((percent / 100.0 * self.count as f64).ceil() as u64).clamp(1, self.count);
+        let mut seen = 0;
+
+        for (idx, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let (low, high) = bucket_bounds(idx);
+                let mid = low + (high - low) / 2;
+                return Duration::from_micros(mid.clamp(self.min_micros, self.max_micros));
+            }
+        }
+
+        self.max()
+    }
+}
+
+fn bucket_index(micros: u64) -> usize {
+    if micros < LINEAR_BUCKETS {
+        return micros as usize;
+    }
+
+    // for values in [2^exp, 2^(exp+1)), the top SUB_BUCKET_BITS + 1 bits (the highest of which is
+    // always set) pick the bucket
+    let exp = 63 - micros.leading_zeros();
+    let shift = exp - SUB_BUCKET_BITS;
+    let mantissa = micros >> shift;
+
+    (u64::from(shift) * (1 << SUB_BUCKET_BITS) + mantissa) as usize
+}
+
+// the lowest and highest values (inclusive) landing in the given bucket
+fn bucket_bounds(idx: usize) -> (u64, u64) {
+    let idx = idx as u64;
+    if idx < LINEAR_BUCKETS {
+        return (idx, idx);
+    }
+
+    let sub_buckets = 1 << SUB_BUCKET_BITS;
+    let shift = idx / sub_buckets - 1;
+    let mantissa = idx % sub_buckets + sub_buckets;
+    let low = mantissa << shift;
+
+    (low, low + ((1 << shift) - 1))
+}
+
+#[test]
+fn test_bucket_bounds_contain_their_values() {
+    for micros in (0..100_000).chain([1 << 40, u64::MAX / 3, u64::MAX]) {
+        let (low, high) = bucket_bounds(bucket_index(micros));
+        assert!(
+            low <= micros && micros <= high,
+            "{} not in [{}, {}]",
+            micros,
+            low,
+            high
+        );
+    }
+}
+
+#[test]
+fn test_buckets_are_contiguous() {
+    for idx in 0..2000 {
+        assert_eq!(
+            bucket_bounds(idx).1 + 1,
+            bucket_bounds(idx + 1).0,
+            "{}",
+            idx
+        );
+    }
+}
+
+#[test]
+fn test_percentiles() {
+    let mut hist = Histogram::default();
+    for ms in 1..=100 {
+        hist.record(Duration::from_millis(ms));
+    }
+
+    assert_eq!(100, hist.count());
+    assert_eq!(Duration::from_millis(1), hist.min());
+    assert_eq!(Duration::from_millis(100), hist.max());
+    assert_eq!(Duration::from_micros(50_500), hist.mean());
+
+    for (percent, expected_ms) in [(50.0, 50.0), (90.0, 90.0), (99.0, 99.0), (100.0, 100.0)] {
+        let actual_ms = hist.percentile(percent).as_secs_f64() * 1000.0;
+        assert!(
+            (actual_ms - expected_ms).abs() / expected_ms < 0.02,
+            "p{}: expected ~{}ms, got {}ms",
+            percent,
+            expected_ms,
+            actual_ms
+        );
+    }
+}
+
+#[test]
+fn test_merge() {
+    let mut fast = Histogram::default();
+    let mut slow = Histogram::default();
+    for _ in 0..90 {
+        fast.record(Duration::from_millis(10));
+    }
+    for _ in 0..10 {
+        slow.record(Duration::from_secs(2));
+    }
+
+    fast.merge(&slow);
+
+    assert_eq!(100, fast.count());
+    assert_eq!(Duration::from_millis(10), fast.min());
+    assert_eq!(Duration::from_secs(2), fast.max());
+    assert!(fast.percentile(90.0) < Duration::from_millis(11));
+    assert!(fast.percentile(95.0) > Duration::from_millis(1900));
+}
+
+#[test]
+fn test_merge_into_empty() {
+    let mut empty = Histogram::default();
+    let mut other = Histogram::default();
+    other.record(Duration::from_millis(5));
+
+    empty.merge(&other);
+
+    assert_eq!(Duration::from_millis(5), empty.min());
+    assert_eq!(Duration::from_millis(5), empty.max());
+}
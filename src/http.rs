@@ -1,16 +1,24 @@
 use nanoserde::DeRon;
 use ureq::{Agent, AgentBuilder};
+use url::Url;
 
 use std::collections::HashMap;
+use std::fmt::Write;
 
 use crate::config_duration::ConfigDuration;
 use crate::grunt::Grunt;
+use crate::pipe_contents::PipeContents;
 use crate::pipeline::action::{ConfigActionMap, PipelineAction, Reference};
 use crate::pipeline::step_handler::{
     StepCompletion, StepError, StepHandler, StepHandlerInit, StepResult,
 };
 use crate::pipeline::Pipeline;
 
+#[cfg(test)]
+use crate::lua::LuaForPipeline;
+#[cfg(test)]
+use crate::targets::{parse_base_url, Targets};
+
 #[derive(Clone, Debug, DeRon)]
 pub enum Action {
     // http verbs. this section could be fewer LOC with macros eg
@@ -22,6 +30,8 @@ pub enum Action {
     // TODO: figure out what, if anything, are appropriate guardrails for a PATCH verb
     Delete {
         url: String,
+        name: Option<String>,
+        target: Option<String>,
         headers: Option<ConfigActionMap>,
         params: Option<ConfigActionMap>,
         body: Option<Reference>,
@@ -29,6 +39,8 @@ pub enum Action {
     },
     Get {
         url: String,
+        name: Option<String>,
+        target: Option<String>,
         headers: Option<ConfigActionMap>,
         params: Option<ConfigActionMap>,
         body: Option<Reference>,
@@ -36,6 +48,8 @@ pub enum Action {
     },
     Head {
         url: String,
+        name: Option<String>,
+        target: Option<String>,
         headers: Option<ConfigActionMap>,
         params: Option<ConfigActionMap>,
         body: Option<Reference>,
@@ -43,6 +57,8 @@ pub enum Action {
     },
     Post {
         url: String,
+        name: Option<String>,
+        target: Option<String>,
         headers: Option<ConfigActionMap>,
         params: Option<ConfigActionMap>,
         body: Option<Reference>,
@@ -50,6 +66,8 @@ pub enum Action {
     },
     Put {
         url: String,
+        name: Option<String>,
+        target: Option<String>,
         headers: Option<ConfigActionMap>,
         params: Option<ConfigActionMap>,
         body: Option<Reference>,
@@ -58,6 +76,16 @@ pub enum Action {
 }
 
 impl Action {
+    pub fn method(&self) -> &'static str {
+        match self {
+            Self::Delete { .. } => "DELETE",
+            Self::Get { .. } => "GET",
+            Self::Head { .. } => "HEAD",
+            Self::Post { .. } => "POST",
+            Self::Put { .. } => "PUT",
+        }
+    }
+
     pub fn url(&self) -> &String {
         match self {
             Self::Delete { url, .. }
@@ -68,6 +96,28 @@ impl Action {
         }
     }
 
+    /// the name given to this request for reporting, if any (see also PipelineAction::name)
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Delete { name, .. }
+            | Self::Get { name, .. }
+            | Self::Head { name, .. }
+            | Self::Post { name, .. }
+            | Self::Put { name, .. } => name.as_deref(),
+        }
+    }
+
+    /// the name of the situation target this request is sent to, or None for the base URL
+    pub fn target(&self) -> Option<&str> {
+        match self {
+            Self::Delete { target, .. }
+            | Self::Get { target, .. }
+            | Self::Head { target, .. }
+            | Self::Post { target, .. }
+            | Self::Put { target, .. } => target.as_deref(),
+        }
+    }
+
     pub fn headers(&self) -> Option<&ConfigActionMap> {
         match self {
             Self::Delete { headers, .. }
@@ -108,6 +158,16 @@ impl Action {
         }
     }
 }
+
+// an HTTP action as it's about to be sent, its references resolved
+#[derive(Debug)]
+struct Request {
+    url: Url,
+    headers: HashMap<String, String>,
+    params: HashMap<String, String>,
+    body: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct HttpHandler {
     agent: Agent,
@@ -126,12 +186,13 @@ impl StepHandler for HttpHandler {
     fn step(&self, pl: &Pipeline, action: &PipelineAction) -> StepResult {
         match action {
             PipelineAction::Http(verb) => {
-                let path = pl
-                    .base_url
-                    .join(verb.url())
-                    .map_err(StepError::UrlParsing)
-                    .map(|url| url.to_string())?;
+                let request = self.resolve(pl, verb)?;
+
+                if pl.dry_run {
+                    return Ok(self.dry_run_step(pl, verb, request));
+                }
 
+                let path = request.url.to_string();
                 let mut req = match verb {
                     Action::Delete { .. } => self.agent.delete(&path),
                     Action::Get { .. } => self.agent.get(&path),
@@ -144,20 +205,16 @@ impl StepHandler for HttpHandler {
                     req = req.timeout(timeout.into())
                 }
 
-                for (key, val) in self.build_request_hashmap(pl, verb.headers())? {
+                for (key, val) in request.headers {
                     req = req.set(&key, &val);
                 }
 
-                for (key, val) in self.build_request_hashmap(pl, verb.params())? {
+                for (key, val) in request.params {
                     req = req.query(&key, &val);
                 }
 
-                let req_result = match verb.body() {
-                    Some(body) => req.send_bytes(
-                        &body
-                            .try_into_string_given_pipe_data(pl.lua, pl.data.as_ref())?
-                            .into_bytes(),
-                    ),
+                let req_result = match request.body {
+                    Some(body) => req.send_bytes(&body.into_bytes()),
                     None => req.call(),
                 };
 
@@ -177,6 +234,80 @@ impl StepHandler for HttpHandler {
 }
 
 impl HttpHandler {
+    // joins the action's URL onto its target, and resolves its headers, params, and body from
+    // whatever's in the pipe
+    fn resolve(&self, pl: &Pipeline, verb: &Action) -> Result<Request, StepError> {
+        Ok(Request {
+            // Situation::from_spec refuses actions naming targets that don't exist
+            url: pl
+                .targets
+                .resolve(verb.target())
+                .ok_or(StepError::InvalidActionInContext)?
+                .join(verb.url())
+                .map_err(StepError::UrlParsing)?,
+            headers: self.build_request_hashmap(pl, verb.headers())?,
+            params: self.build_request_hashmap(pl, verb.params())?,
+            body: verb
+                .body()
+                .map(|body| body.try_into_string_given_pipe_data(pl.lua, pl.data.as_ref()))
+                .transpose()?,
+        })
+    }
+
+    // prints the fully-resolved request in place of sending it, and puts an empty 200 response
+    // in the pipe so that any Lua functions further down the sequence still have something to
+    // chew on
+    fn dry_run_step(&self, pl: &Pipeline, verb: &Action, request: Request) -> StepCompletion {
+        print!("{}", self.dry_run_plan(pl, verb, request));
+
+        StepCompletion::Normal(Some(PipeContents::HttpResponse {
+            body: Vec::new(),
+            content_type: String::new(),
+            headers: HashMap::new(),
+            status_code: 200,
+        }))
+    }
+
+    // the lines a dry run prints for a request: its method and URL (query parameters included),
+    // then its headers, then its body, with parameters and headers sorted by name
+    fn dry_run_plan(&self, pl: &Pipeline, verb: &Action, request: Request) -> String {
+        let Request {
+            mut url,
+            headers,
+            params,
+            body,
+        } = request;
+
+        let mut params: Vec<_> = params.iter().collect();
+        params.sort();
+        for (key, val) in params {
+            url.query_pairs_mut().append_pair(key, val);
+        }
+
+        let mut out = format!(
+            "[{}] step {}: {} {}\n",
+            pl.grunt.name,
+            pl.step_index(),
+            verb.method(),
+            url
+        );
+
+        let mut headers: Vec<_> = headers.iter().collect();
+        headers.sort();
+        for (key, val) in headers {
+            let _ = writeln!(out, "[{}]     {}: {}", pl.grunt.name, key, val);
+        }
+
+        if let Some(body) = body {
+            let _ = writeln!(out, "[{}]", pl.grunt.name);
+            for line in body.lines() {
+                let _ = writeln!(out, "[{}]     {}", pl.grunt.name, line);
+            }
+        }
+
+        out
+    }
+
     fn build_request_hashmap(
         &self,
         pl: &Pipeline,
@@ -199,3 +330,87 @@ impl HttpHandler {
         }
     }
 }
+
+#[cfg(test)]
+const SCRIPT: &str = r#"
+return {
+    make_request = function()
+        return { token = "Bearer abc", page = 2, body = "{\n  \"id\": 1\n}" }
+    end,
+    status_of = function(response)
+        return response.status_code
+    end,
+}
+"#;
+
+#[cfg(test)]
+fn targets() -> Targets {
+    Targets {
+        base_url: parse_base_url("http://localhost/api").unwrap(),
+        named: HashMap::new(),
+    }
+}
+
+#[test]
+fn test_dry_run_resolves_lua_table_values() {
+    let grunt = Grunt::test(
+        r#"
+            LuaFunction("make_request"),
+            Http(Post(
+                url: "items",
+                headers: { "Authorization": LuaTableValue("token") },
+                params: { "page": LuaTableValue("page"), "sort": Value("name") },
+                body: LuaTableValue("body"),
+            )),
+        "#,
+    );
+    let targets = targets();
+    let lua = LuaForPipeline::test(SCRIPT);
+    let mut pl = Pipeline::new(&grunt, &targets, Some(&lua), true, None).unwrap();
+    assert!(matches!(pl.next(), Some(Ok(_))));
+
+    let verb = match &grunt.persona.sequence[1] {
+        PipelineAction::Http(verb) => verb,
+        _ => unreachable!(),
+    };
+    let handler = HttpHandler::new(&grunt).unwrap();
+    let request = handler.resolve(&pl, verb).unwrap();
+
+    assert_eq!(
+        "[Buyer 0] step 1: POST http://localhost/api/items?page=2&sort=name
+[Buyer 0]     Authorization: Bearer abc
+[Buyer 0]
+[Buyer 0]     {
+[Buyer 0]       \"id\": 1
+[Buyer 0]     }
+",
+        handler.dry_run_plan(&pl, verb, request)
+    );
+}
+
+#[test]
+fn test_dry_run_response_reaches_next_step() {
+    let grunt = Grunt::test(r#"Http(Get(url: "items")), LuaFunction("status_of")"#);
+    let targets = targets();
+    let lua = LuaForPipeline::test(SCRIPT);
+    let mut pl = Pipeline::new(&grunt, &targets, Some(&lua), true, None).unwrap();
+
+    assert!(matches!(pl.next(), Some(Ok(_))));
+    assert!(matches!(
+        &pl.data,
+        Some(PipeContents::HttpResponse {
+            status_code: 200,
+            body,
+            ..
+        }) if body.is_empty()
+    ));
+
+    assert!(matches!(pl.next(), Some(Ok(_))));
+    let status_code = match &pl.data {
+        Some(PipeContents::LuaReference(rkey)) => {
+            lua.context(|ctx| ctx.registry_value::<i64>(rkey)).unwrap()
+        }
+        other => panic!("expected a Lua value in the pipe, got {:?}", other),
+    };
+    assert_eq!(200, status_code);
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// where ${VAR} placeholders in situation configs get their values: KEY=VALUE pairs given on the
+/// command line with --set first, then the environment
+#[derive(Clone, Debug, Default)]
+pub struct Variables {
+    overrides: HashMap<String, String>,
+}
+
+impl Variables {
+    pub fn new(overrides: &[SetVariable]) -> Self {
+        Self {
+            overrides: overrides
+                .iter()
+                .map(|var| (var.name.clone(), var.value.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.overrides
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok())
+    }
+}
+
+/// a KEY=VALUE pair given on the command line with --set
+#[derive(Clone, Debug)]
+pub struct SetVariable {
+    pub name: String,
+    pub value: String,
+}
+
+impl FromStr for SetVariable {
+    type Err = String;
+
+    fn from_str(it: &str) -> Result<Self, Self::Err> {
+        match it.split_once('=') {
+            Some((name, value)) if is_valid_name(name) => Ok(Self {
+                name: name.into(),
+                value: value.into(),
+            }),
+            _ => Err(format!(
+                "expected KEY=VALUE, where KEY is made of letters, digits, and underscores, got \"{}\"",
+                it
+            )),
+        }
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+/// replaces every ${VAR} in the raw text of a RON config with its value. values landing inside a
+/// string literal are escaped such that they can't end the string early, while those landing
+/// outside of one (say, a grunt's count) are inserted verbatim. $${ is left in place as a
+/// literal ${, and comments are left alone entirely, placeholders and quotes within them included
+pub fn interpolate(text: &str, vars: &Variables) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.char_indices();
+    let mut in_string = false;
+    let mut line = 1;
+
+    while let Some((idx, ch)) = chars.next() {
+        match ch {
+            '\n' => line += 1,
+
+            // inside a string literal, whatever follows a backslash is never a quote that ends
+            // the string, nor the start of a placeholder
+            '\\' if in_string => {
+                out.push(ch);
+                if let Some((_, escaped)) = chars.next() {
+                    out.push(escaped);
+                }
+                continue;
+            }
+
+            '"' => in_string = !in_string,
+
+            '/' if !in_string
+                && (text[idx..].starts_with("//") || text[idx..].starts_with("/*")) =>
+            {
+                // line comments run up to (but not including) the end of the line, which is
+                // counted as usual, and block comments through the */ that closes them
+                let end = if text[idx..].starts_with("//") {
+                    text[idx..].find('\n').map_or(text.len(), |end| idx + end)
+                } else {
+                    text[idx + 2..]
+                        .find("*/")
+                        .map_or(text.len(), |end| idx + 2 + end + 2)
+                };
+
+                let comment = &text[idx..end];
+                line += comment.matches('\n').count();
+                out.push_str(comment);
+
+                for _ in comment.chars().skip(1) {
+                    chars.next();
+                }
+                continue;
+            }
+
+            '$' if text[idx..].starts_with("$${") => {
+                chars.next();
+                out.push('$');
+                continue;
+            }
+
+            '$' if text[idx..].starts_with("${") => {
+                let rest = &text[idx + 2..];
+                let name = rest
+                    .find('}')
+                    .map(|end| &rest[..end])
+                    .filter(|name| is_valid_name(name))
+                    .ok_or_else(|| format!("line {}: malformed ${{...}} placeholder", line))?;
+
+                let value = vars
+                    .get(name)
+                    .ok_or_else(|| format!("line {}: variable {} is not defined", line, name))?;
+
+                if in_string {
+                    out.push_str(&value.replace('\\', "\\\\").replace('"', "\\\""));
+                } else {
+                    out.push_str(&value);
+                }
+
+                // skip past the rest of the placeholder: the {, the name, and the }
+                for _ in 0..name.len() + 2 {
+                    chars.next();
+                }
+                continue;
+            }
+
+            _ => {}
+        }
+
+        out.push(ch);
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+fn vars(pairs: &[(&str, &str)]) -> Variables {
+    Variables {
+        overrides: pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+    }
+}
+
+#[test]
+fn test_interpolate() {
+    let vars = vars(&[("HOST", "staging.local"), ("COUNT", "20")]);
+
+    assert_eq!(
+        Ok("(url: \"http://staging.local/\", count: 20)".into()),
+        interpolate("(url: \"http://${HOST}/\", count: ${COUNT})", &vars)
+    );
+}
+
+#[test]
+fn test_interpolate_escapes_within_strings() {
+    let vars = vars(&[("TOKEN", "a\"b\\c")]);
+
+    assert_eq!(
+        Ok("Value(\"Bearer a\\\"b\\\\c\")".into()),
+        interpolate("Value(\"Bearer ${TOKEN}\")", &vars)
+    );
+}
+
+#[test]
+fn test_interpolate_literal_placeholder() {
+    assert_eq!(
+        Ok("Value(\"${NOT_A_VAR}\")".into()),
+        interpolate("Value(\"$${NOT_A_VAR}\")", &Variables::default())
+    );
+}
+
+#[test]
+fn test_interpolate_skips_comments() {
+    let vars = vars(&[("TOKEN", "a\"b")]);
+
+    assert_eq!(
+        Ok("// was \"${OLD_TOKEN}\n/* nor \"${OLDER_TOKEN}\n */ Value(\"a\\\"b\")".into()),
+        interpolate(
+            "// was \"${OLD_TOKEN}\n/* nor \"${OLDER_TOKEN}\n */ Value(\"${TOKEN}\")",
+            &vars
+        )
+    );
+
+    let err = interpolate("/*\n*/\n${OLD_TOKEN}", &vars).unwrap_err();
+    assert!(err.contains("line 3"), "{}", err);
+}
+
+#[test]
+fn test_interpolate_undefined() {
+    let err = interpolate(
+        "(\n    count: ${SEATRIAL_TEST_SURELY_UNDEFINED},\n)",
+        &Variables::default(),
+    )
+    .unwrap_err();
+
+    assert!(err.contains("line 2"), "{}", err);
+    assert!(err.contains("SEATRIAL_TEST_SURELY_UNDEFINED"), "{}", err);
+}
+
+#[test]
+fn test_set_variable_from_str() {
+    let var = SetVariable::from_str("TOKEN=abc=def").unwrap();
+
+    assert_eq!("TOKEN", var.name);
+    assert_eq!("abc=def", var.value);
+
+    assert!(SetVariable::from_str("TOKEN").is_err());
+    assert!(SetVariable::from_str("NOT A NAME=1").is_err());
+}
+
+#[test]
+fn test_set_variable_beats_environment() {
+    let vars = Variables::new(&[SetVariable::from_str("PATH=overridden").unwrap()]);
+
+    assert_eq!(Some("overridden".into()), vars.get("PATH"));
+}
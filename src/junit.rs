@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::events::StepEvent;
+use crate::situation::Situation;
+
+// the kinds of action (see PipelineAction::kind) reported as test cases
+const TESTED_ACTIONS: [&str; 2] = ["Validator", "Combinator"];
+
+#[derive(Debug, Default)]
+struct TestCase {
+    name: String,
+    runs: u64,
+    time: Duration,
+
+    // each distinct failure message, and how many times it came up
+    failures: BTreeMap<String, u64>,
+    failure_kind: Option<&'static str>,
+
+    // likewise for warnings raised by runs which otherwise passed
+    warnings: BTreeMap<String, u64>,
+}
+
+impl TestCase {
+    fn failure_count(&self) -> u64 {
+        self.failures.values().sum()
+    }
+
+    fn render(&self, out: &mut String, classname: &str) {
+        let _ = write!(
+            out,
+            "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+            escape(classname),
+            escape(&self.name),
+            self.time.as_secs_f64()
+        );
+
+        if self.runs > 0 && self.failures.is_empty() && self.warnings.is_empty() {
+            let _ = writeln!(out, "/>");
+            return;
+        }
+
+        let _ = writeln!(out, ">");
+
+        if self.runs == 0 {
+            let _ = writeln!(out, "      <skipped message=\"never reached\"/>");
+        }
+
+        // the most common message stands in for the rest, which are listed in the body
+        if let Some((message, _)) = self.failures.iter().max_by_key(|(_, count)| **count) {
+            let _ = writeln!(
+                out,
+                "      <failure message=\"{}\" type=\"{}\">{}</failure>",
+                escape(message),
+                self.failure_kind.unwrap_or("validation"),
+                escape(&tally(
+                    &format!("failed {} of {} runs:", self.failure_count(), self.runs),
+                    &self.failures
+                ))
+            );
+        }
+
+        if !self.warnings.is_empty() {
+            let warned: u64 = self.warnings.values().sum();
+            let _ = writeln!(
+                out,
+                "      <system-out>{}</system-out>",
+                escape(&tally(
+                    &format!("{} warnings across {} runs:", warned, self.runs),
+                    &self.warnings
+                ))
+            );
+        }
+
+        let _ = writeln!(out, "    </testcase>");
+    }
+}
+
+/// the outcomes of every Validator and Combinator step of every persona, aggregated across
+/// grunts and iterations, for the JUnit XML report written with --junit. each situation makes a
+/// test suite, and each such step a test case within it
+#[derive(Debug, Default)]
+pub struct JunitReport {
+    // keyed by situation, then by persona and the step's index in its sequence
+    suites: BTreeMap<String, BTreeMap<(String, usize), TestCase>>,
+}
+
+impl JunitReport {
+    /// a report with a test case for every tested step of every persona, such that steps no
+    /// grunt ever reached show up as skipped, rather than not at all
+    pub fn new(situations: &[Situation]) -> Self {
+        let mut report = Self::default();
+
+        for situation in situations {
+            for grunt in &situation.grunts {
+                let steps = grunt
+                    .persona
+                    .sequence
+                    .iter()
+                    .zip(&grunt.persona.step_names)
+                    .enumerate();
+
+                for (idx, (step, name)) in steps {
+                    if TESTED_ACTIONS.contains(&step.kind()) {
+                        report.case(&situation.name, &grunt.base_name, idx, name);
+                    }
+                }
+            }
+        }
+
+        report
+    }
+
+    fn case(&mut self, situation: &str, persona: &str, step: usize, name: &str) -> &mut TestCase {
+        self.suites
+            .entry(situation.into())
+            .or_default()
+            .entry((persona.into(), step))
+            .or_insert_with(|| TestCase {
+                name: name.into(),
+                ..TestCase::default()
+            })
+    }
+
+    pub fn record(&mut self, event: &StepEvent) {
+        if !TESTED_ACTIONS.contains(&event.action) {
+            return;
+        }
+
+        let case = self.case(&event.situation, &event.persona, event.step, &event.name);
+        case.runs += 1;
+        case.time += event.latency;
+
+        match event.error {
+            Some(kind) => {
+                let message = event.error_message.clone().unwrap_or_else(|| kind.into());
+                *case.failures.entry(message).or_default() += 1;
+                case.failure_kind.get_or_insert(kind);
+            }
+            None => {
+                for warning in &event.warnings {
+                    *case.warnings.entry(warning.clone()).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let cases = || self.suites.values().flat_map(BTreeMap::values);
+        let time: Duration = cases().map(|case| case.time).sum();
+
+        let mut out = String::new();
+        let _ = writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+        let _ = writeln!(
+            out,
+            "<testsuites name=\"seatrial\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+            cases().count(),
+            cases().filter(|case| case.failure_count() > 0).count(),
+            cases().filter(|case| case.runs == 0).count(),
+            time.as_secs_f64()
+        );
+
+        for (situation, cases) in &self.suites {
+            let time: Duration = cases.values().map(|case| case.time).sum();
+            let _ = writeln!(
+                out,
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\">",
+                escape(situation),
+                cases.len(),
+                cases.values().filter(|case| case.failure_count() > 0).count(),
+                cases.values().filter(|case| case.runs == 0).count(),
+                time.as_secs_f64()
+            );
+
+            for ((persona, _), case) in cases {
+                case.render(&mut out, &format!("{}.{}", situation, persona));
+            }
+
+            let _ = writeln!(out, "  </testsuite>");
+        }
+
+        let _ = writeln!(out, "</testsuites>");
+
+        out
+    }
+}
+
+// a heading, followed by each message with how many times it came up, most common first
+fn tally(heading: &str, messages: &BTreeMap<String, u64>) -> String {
+    let mut messages: Vec<(&String, &u64)> = messages.iter().collect();
+    messages.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    let mut out = String::from(heading);
+    for (message, count) in messages {
+        let _ = write!(out, "\n{} x {}", count, message);
+    }
+
+    out
+}
+
+// for use in both attributes and text. XML 1.0 has no way at all to represent most control
+// characters, so they're dropped
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            _ if c.is_control() => {}
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+#[test]
+fn test_render() {
+    let mut report = JunitReport::default();
+    report.case("checkout", "Buyer", 5, "NoneOf(...)");
+
+    for (persona, step, error_message, warnings) in [
+        ("Buyer", 1, None, vec![]),
+        ("Buyer", 1, Some("expected 200, got <500>"), vec![]),
+        ("Buyer", 1, Some("expected 200, got <500>"), vec![]),
+        ("Buyer", 1, Some("expected 200, got 404"), vec![]),
+        ("Browser", 3, None, vec![]),
+        (
+            "Browser",
+            3,
+            None,
+            vec![String::from("header X-Foo missing")],
+        ),
+    ] {
+        report.record(&StepEvent {
+            persona: persona.into(),
+            step,
+            warnings,
+            error: error_message.map(|_| "validation"),
+            error_message: error_message.map(String::from),
+            ..StepEvent::test_validator("AssertStatusCode(200)")
+        });
+    }
+
+    // not a validator, and thus not a test case
+    report.record(&StepEvent {
+        action: "Http",
+        ..StepEvent::test_validator("AssertStatusCode(200)")
+    });
+
+    assert_eq!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="seatrial" tests="3" failures="1" skipped="1" time="0.006">
+  <testsuite name="checkout" tests="3" failures="1" errors="0" skipped="1" time="0.006">
+    <testcase classname="checkout.Browser" name="AssertStatusCode(200)" time="0.002">
+      <system-out>1 warnings across 2 runs:
+1 x header X-Foo missing</system-out>
+    </testcase>
+    <testcase classname="checkout.Buyer" name="AssertStatusCode(200)" time="0.004">
+      <failure message="expected 200, got &lt;500&gt;" type="validation">failed 3 of 4 runs:
+2 x expected 200, got &lt;500&gt;
+1 x expected 200, got 404</failure>
+    </testcase>
+    <testcase classname="checkout.Buyer" name="NoneOf(...)" time="0.000">
+      <skipped message="never reached"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#,
+        report.render()
+    );
+}
+
+#[test]
+fn test_passing_case_is_self_closing() {
+    let mut report = JunitReport::default();
+    report.record(&StepEvent {
+        step: 1,
+        ..StepEvent::test_validator("AssertStatusCode(200)")
+    });
+
+    assert!(report.render().contains(
+        "<testcase classname=\"checkout.Buyer\" name=\"AssertStatusCode(200)\" time=\"0.001\"/>"
+    ));
+}
+
+#[test]
+fn test_escape() {
+    assert_eq!(
+        "a &amp; &quot;b&quot; &lt;c&gt; &apos;d&apos;\n",
+        escape("a & \"b\" <c> 'd'\n\u{1b}")
+    );
+}
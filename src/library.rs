@@ -0,0 +1,219 @@
+use nanoserde::DeRon;
+
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use crate::interpolation::{interpolate, Variables};
+use crate::persona::PersonaSpec;
+use crate::pipeline::action::{ControlFlow, PipelineAction as PA};
+
+#[cfg(test)]
+use crate::validator::Action as ValidatorAction;
+
+/// the contents of a RON file listed in a situation's includes: named personas grunts may use in
+/// place of an inline one, and named sequence fragments any sequence may Include
+#[derive(Clone, Debug, DeRon)]
+pub struct LibrarySpec {
+    personas: Option<HashMap<String, PersonaSpec>>,
+    fragments: Option<HashMap<String, Vec<PA>>>,
+}
+
+/// every persona and fragment available to a situation, merged from all of its includes
+#[derive(Clone, Debug, Default)]
+pub struct Library {
+    personas: HashMap<String, PersonaSpec>,
+    fragments: HashMap<String, Vec<PA>>,
+}
+
+impl Library {
+    /// reads the library file at the given path (placeholders and all, as with situation files)
+    /// into this library. names must be unique across every file included by a situation
+    pub fn include(&mut self, path: &Path, vars: &Variables) -> Result<(), String> {
+        let text = read_to_string(path).map_err(|err| err.to_string())?;
+        let spec: LibrarySpec =
+            DeRon::deserialize_ron(&interpolate(&text, vars)?).map_err(|err| err.to_string())?;
+
+        for (name, persona) in spec.personas.unwrap_or_default() {
+            if self.personas.insert(name.clone(), persona).is_some() {
+                return Err(format!("persona \"{}\" is defined more than once", name));
+            }
+        }
+
+        for (name, fragment) in spec.fragments.unwrap_or_default() {
+            if self.fragments.insert(name.clone(), fragment).is_some() {
+                return Err(format!("fragment \"{}\" is defined more than once", name));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn persona(&self, name: &str) -> Result<&PersonaSpec, String> {
+        self.personas
+            .get(name)
+            .ok_or_else(|| format!("persona \"{}\" is not defined by any included file", name))
+    }
+
+    /// replaces every Include in the sequence with the steps of the fragment it names,
+    /// recursively. GoTo indices, which are written relative to the sequence (or fragment) they
+    /// appear in, are adjusted to point at the same step once everything has been spliced
+    /// together
+    pub fn expand_sequence(&self, sequence: &[PA]) -> Result<Vec<PA>, String> {
+        self.expand(sequence, &mut Vec::new())
+    }
+
+    fn expand<'a>(&'a self, sequence: &[PA], stack: &mut Vec<&'a str>) -> Result<Vec<PA>, String> {
+        let mut expanded: Vec<PA> = Vec::with_capacity(sequence.len());
+
+        // where each step as written ended up, and which of the expanded steps are GoTos written
+        // here (as opposed to within a fragment, whose indices have already been adjusted)
+        let mut positions: Vec<usize> = Vec::with_capacity(sequence.len() + 1);
+        let mut own_gotos: Vec<usize> = Vec::new();
+
+        for step in sequence {
+            positions.push(expanded.len());
+
+            match step {
+                PA::Include(name) => {
+                    let (name, fragment) = self.fragments.get_key_value(name).ok_or_else(|| {
+                        format!("fragment \"{}\" is not defined by any included file", name)
+                    })?;
+
+                    if stack.contains(&name.as_str()) {
+                        return Err(format!("fragment \"{}\" includes itself", name));
+                    }
+
+                    stack.push(name);
+                    let offset = expanded.len();
+                    for mut step in self.expand(fragment, stack)? {
+                        if let PA::ControlFlow(ControlFlow::GoTo { index, .. }) = &mut step {
+                            *index += offset;
+                        }
+                        expanded.push(step);
+                    }
+                    stack.pop();
+                }
+
+                PA::ControlFlow(ControlFlow::GoTo { .. }) => {
+                    own_gotos.push(expanded.len());
+                    expanded.push(step.clone());
+                }
+
+                _ => expanded.push(step.clone()),
+            }
+        }
+
+        // a GoTo to just past the last step ends the pass, as it did before expansion; one even
+        // further out stays just as far out, for seatrial lint to complain about
+        let expanded_len = expanded.len();
+        positions.push(expanded_len);
+
+        for idx in own_gotos {
+            if let PA::ControlFlow(ControlFlow::GoTo { index, .. }) = &mut expanded[idx] {
+                *index = match positions.get(*index) {
+                    Some(position) => *position,
+                    None => expanded_len + (*index - sequence.len()),
+                };
+            }
+        }
+
+        Ok(expanded)
+    }
+}
+
+#[cfg(test)]
+fn goto(index: usize) -> PA {
+    PA::ControlFlow(ControlFlow::GoTo {
+        index,
+        max_times: None,
+    })
+}
+
+#[cfg(test)]
+fn goto_index(step: &PA) -> usize {
+    match step {
+        PA::ControlFlow(ControlFlow::GoTo { index, .. }) => *index,
+        _ => panic!("expected a GoTo, got {:?}", step),
+    }
+}
+
+#[cfg(test)]
+fn library(fragments: &[(&str, Vec<PA>)]) -> Library {
+    Library {
+        personas: HashMap::new(),
+        fragments: fragments
+            .iter()
+            .map(|(name, fragment)| (name.to_string(), fragment.clone()))
+            .collect(),
+    }
+}
+
+#[test]
+fn test_expand_remaps_gotos() {
+    let library = library(&[(
+        "login",
+        vec![
+            PA::LuaFunction("credentials".into()),
+            PA::Validator(ValidatorAction::AssertStatusCode(200)),
+            goto(0),
+        ],
+    )]);
+
+    let expanded = library
+        .expand_sequence(&[
+            PA::LuaFunction("setup".into()),
+            PA::Include("login".into()),
+            PA::LuaFunction("browse".into()),
+            goto(2),
+            goto(5),
+        ])
+        .unwrap();
+
+    assert_eq!(7, expanded.len());
+
+    // the fragment's GoTo to its own first step now points at wherever that step landed
+    assert_eq!(1, goto_index(&expanded[3]));
+
+    // the sequence's GoTos to "browse" and to just past the end follow their targets
+    assert_eq!(4, goto_index(&expanded[5]));
+    assert_eq!(7, goto_index(&expanded[6]));
+}
+
+#[test]
+fn test_expand_nested() {
+    let library = library(&[
+        ("outer", vec![PA::Include("inner".into()), goto(0)]),
+        (
+            "inner",
+            vec![PA::LuaFunction("a".into()), PA::LuaFunction("b".into())],
+        ),
+    ]);
+
+    let expanded = library
+        .expand_sequence(&[PA::LuaFunction("first".into()), PA::Include("outer".into())])
+        .unwrap();
+
+    assert_eq!(4, expanded.len());
+    assert_eq!(1, goto_index(&expanded[3]));
+}
+
+#[test]
+fn test_expand_rejects_cycles() {
+    let library = library(&[
+        ("a", vec![PA::Include("b".into())]),
+        ("b", vec![PA::Include("a".into())]),
+    ]);
+
+    assert!(library
+        .expand_sequence(&[PA::Include("a".into())])
+        .unwrap_err()
+        .contains("includes itself"));
+}
+
+#[test]
+fn test_expand_rejects_unknown_fragments() {
+    assert!(Library::default()
+        .expand_sequence(&[PA::Include("nope".into())])
+        .is_err());
+}
@@ -0,0 +1,579 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use crate::cli::LintArgs;
+use crate::combinator::Action as CombinatorAction;
+use crate::grunt::GruntSpec;
+use crate::lua::LuaForPipeline;
+use crate::pipeline::action::{ControlFlow, PipelineAction as PA, Reference};
+use crate::situation::SituationSpec;
+use crate::sleep_distribution::SleepDistribution;
+use crate::validator::Action as ValidatorAction;
+
+#[cfg(test)]
+use crate::config_duration::ConfigDuration;
+#[cfg(test)]
+use crate::http::Action as HttpAction;
+#[cfg(test)]
+use crate::persona::PersonaSpec;
+
+#[derive(Clone, Debug)]
+pub struct LintProblem {
+    pub situation: String,
+    pub grunt: Option<String>,
+    pub step: Option<usize>,
+    pub message: String,
+}
+
+impl Display for LintProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}]", self.situation)?;
+
+        if let Some(grunt) = &self.grunt {
+            write!(f, " grunt \"{}\"", grunt)?;
+        }
+
+        if let Some(step) = self.step {
+            write!(f, " step {}", step)?;
+        }
+
+        write!(f, ": {}", self.message)
+    }
+}
+
+// what a step might find in the pipe when it runs, as far as we can tell without running anything
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum PipeState {
+    Empty,
+    HttpResponse,
+    LuaValue,
+}
+
+/// lints every situation, printing problems as they're found, and returns how many were found
+pub fn lint_situations(args: &LintArgs) -> usize {
+    let mut problem_count = 0;
+
+    for spec in &args.situations {
+        for problem in lint_situation(spec) {
+            println!("{}", problem);
+            problem_count += 1;
+        }
+    }
+
+    if problem_count == 0 {
+        println!("no problems found");
+    } else {
+        println!("{} problem(s) found", problem_count);
+    }
+
+    problem_count
+}
+
+pub fn lint_situation(spec: &SituationSpec) -> Vec<LintProblem> {
+    let situation = spec.name();
+    let mut problems: Vec<LintProblem> = Vec::new();
+
+    let lua = match spec.canonical_lua_file() {
+        None => {
+            problems.push(LintProblem {
+                situation: situation.clone(),
+                grunt: None,
+                step: None,
+                message: "situations without 'lua_file' are not currently supported".into(),
+            });
+            None
+        }
+        // lint never draws from math.random in earnest, so any seed will do
+        Some(lua_file) => match LuaForPipeline::new(&lua_file, 0) {
+            Ok(lua) => Some(lua),
+            Err(err) => {
+                problems.push(LintProblem {
+                    situation: situation.clone(),
+                    grunt: None,
+                    step: None,
+                    message: format!(
+                        "lua_file {} could not be loaded, so Lua functions were not checked: {}",
+                        lua_file.display(),
+                        err
+                    ),
+                });
+                None
+            }
+        },
+    };
+
+    // the checks a run makes of the situation as a whole, save for an arrival_rate having a
+    // duration to go with it, which may yet be given on the command line. (unknown targets are
+    // caught step by step below, and grunt counts by the phases check, as phases may override
+    // them)
+    let checks = [
+        spec.phases(1, None).err(),
+        spec.arrival_interval().err(),
+        spec.thresholds().err(),
+    ];
+    for err in checks.iter().flatten() {
+        problems.push(LintProblem {
+            situation: situation.clone(),
+            grunt: None,
+            step: None,
+            message: err.message(),
+        });
+    }
+
+    let targets = spec.target_names();
+
+    for grunt_spec in spec.grunt_specs() {
+        let grunt = grunt_spec.display_name();
+
+        for (step, message) in lint_grunt(grunt_spec, lua.as_ref(), &targets) {
+            problems.push(LintProblem {
+                situation: situation.clone(),
+                grunt: Some(grunt.clone()),
+                step,
+                message,
+            });
+        }
+    }
+
+    problems
+}
+
+fn lint_grunt(
+    spec: &GruntSpec,
+    lua: Option<&LuaForPipeline>,
+    targets: &HashSet<&str>,
+) -> Vec<(Option<usize>, String)> {
+    let mut problems: Vec<(Option<usize>, String)> = Vec::new();
+
+    let sequence = &spec.persona().sequence;
+    for (idx, incoming) in incoming_pipe_states(sequence).iter().enumerate() {
+        for message in lint_step(&sequence[idx], incoming, sequence.len(), lua, targets) {
+            problems.push((Some(idx), message));
+        }
+    }
+
+    problems
+}
+
+// works out, for each step in the sequence, every state the pipe could be in when that step runs.
+// a step with no possible incoming states can never be reached
+fn incoming_pipe_states(sequence: &[PA]) -> Vec<HashSet<PipeState>> {
+    let mut incoming: Vec<HashSet<PipeState>> = vec![HashSet::new(); sequence.len()];
+
+    if let Some(first) = incoming.first_mut() {
+        first.insert(PipeState::Empty);
+    }
+
+    // pauses pass the pipe through untouched, so a GoTo landing on one (and thus adding to its
+    // incoming states) may add to those of later steps already visited: go around until nothing
+    // changes
+    loop {
+        let mut changed = false;
+
+        for (idx, step) in sequence.iter().enumerate() {
+            let outgoing: HashSet<PipeState> = match step.unnamed() {
+                // GoTo always clears the pipe on its way to its target, and the step after it can
+                // only be reached by falling through, which GoTo never does (it ends the pipeline
+                // instead once max_times runs out)
+                PA::ControlFlow(ControlFlow::GoTo { index, .. }) => {
+                    if let Some(target) = incoming.get_mut(*index) {
+                        changed |= target.insert(PipeState::Empty);
+                    }
+                    continue;
+                }
+
+                PA::ControlFlow(
+                    ControlFlow::Sleep(_) | ControlFlow::RandomSleep(_) | ControlFlow::Pace(_),
+                ) => incoming[idx].clone(),
+
+                PA::Http(_) => HashSet::from([PipeState::HttpResponse]),
+                PA::Include(_) => HashSet::from([PipeState::Empty]),
+                PA::LuaFunction(_) => HashSet::from([PipeState::LuaValue]),
+                PA::Combinator(_) | PA::Reference(_) | PA::Validator(_) => {
+                    HashSet::from([PipeState::Empty])
+                }
+                PA::Named(..) => unreachable!("unnamed() should have unwrapped Named steps"),
+            };
+
+            if let Some(next) = incoming.get_mut(idx + 1) {
+                for state in outgoing {
+                    changed |= next.insert(state);
+                }
+            }
+        }
+
+        if !changed {
+            return incoming;
+        }
+    }
+}
+
+fn lint_step(
+    step: &PA,
+    incoming: &HashSet<PipeState>,
+    sequence_len: usize,
+    lua: Option<&LuaForPipeline>,
+    targets: &HashSet<&str>,
+) -> Vec<String> {
+    if incoming.is_empty() {
+        return vec!["step can never be reached".into()];
+    }
+
+    match step {
+        PA::ControlFlow(ControlFlow::GoTo { index, max_times }) => {
+            let mut problems = Vec::new();
+
+            if *index > sequence_len {
+                problems.push(format!(
+                    "GoTo index {} is past the end of the sequence (which has {} steps)",
+                    index, sequence_len
+                ));
+            }
+
+            if *max_times == Some(0) {
+                problems.push("GoTo with max_times of 0 ends the pipeline immediately".into());
+            }
+
+            problems
+        }
+
+        PA::ControlFlow(ControlFlow::RandomSleep(SleepDistribution::Uniform { min, max }))
+            if Duration::from(min) > Duration::from(max) =>
+        {
+            vec!["RandomSleep Uniform min is greater than its max".into()]
+        }
+
+        PA::ControlFlow(ControlFlow::Pace(cycle)) if Duration::from(cycle).is_zero() => {
+            vec!["Pace with a cycle time of zero never pauses".into()]
+        }
+
+        PA::ControlFlow(
+            ControlFlow::Sleep(_) | ControlFlow::RandomSleep(_) | ControlFlow::Pace(_),
+        ) => vec![],
+
+        PA::Http(verb) => verb
+            .target()
+            .filter(|target| !targets.contains(target))
+            .map(|target| format!("names target \"{}\", which the situation doesn't define", target))
+            .into_iter()
+            .chain(
+                verb.headers()
+                    .into_iter()
+                    .chain(verb.params())
+                    .flat_map(|refs| refs.values())
+                    .chain(verb.body())
+                    .filter_map(|reference| lint_reference(reference, incoming)),
+            )
+            .map(|problem| format!("request to \"{}\" {}", verb.url(), problem))
+            .collect(),
+
+        PA::LuaFunction(name) => lint_lua_function(name, lua).into_iter().collect(),
+
+        PA::Named(_, step) => lint_step(step, incoming, sequence_len, lua, targets),
+
+        PA::Include(name) => vec![format!(
+            "Include(\"{}\") should have been expanded when the situation was loaded; that it wasn't is an error in seatrial",
+            name
+        )],
+
+        PA::Reference(reference) => vec![format!(
+            "{:?} is not a valid step on its own; References belong in the arguments of an Http action",
+            reference
+        )],
+
+        PA::Validator(validator) => lint_validator(validator, incoming, lua),
+
+        PA::Combinator(combinator) => {
+            let validators = match combinator {
+                CombinatorAction::AllOf(validators)
+                | CombinatorAction::AnyOf(validators)
+                | CombinatorAction::NoneOf(validators) => validators,
+            };
+
+            if validators.is_empty() {
+                return vec![format!("{:?} has no validators to combine", combinator)];
+            }
+
+            validators
+                .iter()
+                .flat_map(|validator| lint_validator(validator, incoming, lua))
+                .collect()
+        }
+    }
+}
+
+fn lint_reference(reference: &Reference, incoming: &HashSet<PipeState>) -> Option<String> {
+    if matches!(reference, Reference::Value(_)) {
+        return None;
+    }
+
+    if incoming.contains(&PipeState::Empty) {
+        Some(format!(
+            "uses {:?}, but the step before it leaves the pipe empty",
+            reference
+        ))
+    } else if incoming.contains(&PipeState::HttpResponse) {
+        Some(format!(
+            "uses {:?}, but the step before it leaves an HTTP response in the pipe, not a Lua value",
+            reference
+        ))
+    } else {
+        None
+    }
+}
+
+fn lint_validator(
+    validator: &ValidatorAction,
+    incoming: &HashSet<PipeState>,
+    lua: Option<&LuaForPipeline>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if incoming.contains(&PipeState::Empty) {
+        problems.push(format!(
+            "{:?} has nothing to validate, as the step before it leaves the pipe empty",
+            validator
+        ));
+    }
+
+    match validator {
+        ValidatorAction::LuaFunction(name) => problems.extend(lint_lua_function(name, lua)),
+        _ => {
+            if incoming.contains(&PipeState::LuaValue) {
+                problems.push(format!(
+                    "{:?} only validates HTTP responses, but the step before it leaves a Lua value in the pipe",
+                    validator
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+fn lint_lua_function(name: &str, lua: Option<&LuaForPipeline>) -> Option<String> {
+    match lua {
+        Some(lua) if !lua.has_user_script_function(name) => Some(format!(
+            "LuaFunction \"{}\" is not exported by the user script",
+            name
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+fn lint_sequence(sequence: Vec<PA>) -> Vec<(Option<usize>, String)> {
+    lint_sequence_with_targets(sequence, &HashSet::new())
+}
+
+#[cfg(test)]
+fn lint_sequence_with_targets(
+    sequence: Vec<PA>,
+    targets: &HashSet<&str>,
+) -> Vec<(Option<usize>, String)> {
+    lint_grunt(
+        &GruntSpec {
+            base_name: None,
+            persona: Some(PersonaSpec {
+                headers: None,
+                sequence,
+                timeout: ConfigDuration::Seconds(30),
+            }),
+            persona_name: None,
+            count: None,
+            ramp_up: None,
+        },
+        None,
+        targets,
+    )
+}
+
+#[cfg(test)]
+fn get(url: &str, body: Option<Reference>) -> PA {
+    PA::Http(HttpAction::Get {
+        url: url.into(),
+        name: None,
+        target: None,
+        headers: None,
+        params: None,
+        body,
+        timeout: None,
+    })
+}
+
+#[test]
+fn test_clean_sequence() {
+    let problems = lint_sequence(vec![
+        PA::LuaFunction("make_body".into()),
+        get("/", Some(Reference::LuaTableValue("body".into()))),
+        PA::Validator(ValidatorAction::AssertStatusCode(200)),
+        PA::ControlFlow(ControlFlow::GoTo {
+            index: 0,
+            max_times: Some(2),
+        }),
+    ]);
+
+    assert!(problems.is_empty(), "{:?}", problems);
+}
+
+#[test]
+fn test_validator_with_empty_pipe() {
+    let problems = lint_sequence(vec![PA::Validator(ValidatorAction::AssertStatusCode(200))]);
+
+    assert_eq!(1, problems.len());
+    assert_eq!(Some(0), problems[0].0);
+}
+
+#[test]
+fn test_bare_reference() {
+    let problems = lint_sequence(vec![PA::Reference(Reference::LuaValue)]);
+
+    assert_eq!(1, problems.len());
+    assert_eq!(Some(0), problems[0].0);
+}
+
+#[test]
+fn test_goto_past_end() {
+    let problems = lint_sequence(vec![
+        get("/", None),
+        PA::ControlFlow(ControlFlow::GoTo {
+            index: 5,
+            max_times: None,
+        }),
+    ]);
+
+    assert_eq!(1, problems.len());
+    assert_eq!(Some(1), problems[0].0);
+}
+
+#[test]
+fn test_lua_reference_after_http() {
+    let problems = lint_sequence(vec![get("/", None), get("/", Some(Reference::LuaValue))]);
+
+    assert_eq!(1, problems.len());
+    assert_eq!(Some(1), problems[0].0);
+}
+
+#[test]
+fn test_unreachable_after_goto() {
+    let problems = lint_sequence(vec![
+        get("/", None),
+        PA::ControlFlow(ControlFlow::GoTo {
+            index: 0,
+            max_times: None,
+        }),
+        get("/never", None),
+    ]);
+
+    assert_eq!(1, problems.len());
+    assert_eq!(Some(2), problems[0].0);
+}
+
+#[test]
+fn test_unknown_target() {
+    let targets = HashSet::from(["auth"]);
+    let problems = lint_sequence_with_targets(
+        vec![
+            PA::Http(HttpAction::Get {
+                url: "/login".into(),
+                name: None,
+                target: Some("auth".into()),
+                headers: None,
+                params: None,
+                body: None,
+                timeout: None,
+            }),
+            PA::Http(HttpAction::Get {
+                url: "/search".into(),
+                name: None,
+                target: Some("search".into()),
+                headers: None,
+                params: None,
+                body: None,
+                timeout: None,
+            }),
+        ],
+        &targets,
+    );
+
+    assert_eq!(1, problems.len());
+    assert_eq!(Some(1), problems[0].0);
+}
+
+#[test]
+fn test_sleep_passes_pipe_through() {
+    let problems = lint_sequence(vec![
+        get("/", None),
+        PA::ControlFlow(ControlFlow::Sleep(ConfigDuration::Seconds(1))),
+        PA::Validator(ValidatorAction::AssertStatusCode(200)),
+    ]);
+
+    assert!(problems.is_empty(), "{:?}", problems);
+}
+
+#[test]
+fn test_goto_onto_pace_empties_pipe() {
+    // the validator sees the response from the Http step falling through, but also the empty pipe
+    // left by the GoTo landing on the Pace just before it
+    let problems = lint_sequence(vec![
+        get("/", None),
+        PA::ControlFlow(ControlFlow::Pace(ConfigDuration::Seconds(5))),
+        PA::Validator(ValidatorAction::AssertStatusCode(200)),
+        PA::ControlFlow(ControlFlow::GoTo {
+            index: 1,
+            max_times: Some(2),
+        }),
+    ]);
+
+    assert_eq!(1, problems.len());
+    assert_eq!(Some(2), problems[0].0);
+}
+
+#[test]
+fn test_uniform_sleep_bounds_reversed() {
+    let problems = lint_sequence(vec![PA::ControlFlow(ControlFlow::RandomSleep(
+        SleepDistribution::Uniform {
+            min: ConfigDuration::Seconds(5),
+            max: ConfigDuration::Seconds(1),
+        },
+    ))]);
+
+    assert_eq!(1, problems.len());
+    assert_eq!(Some(0), problems[0].0);
+}
+
+#[test]
+fn test_situation_checks_a_run_would_make() {
+    let grunts = r#"grunts: [(base_name: "G", persona: (timeout: Seconds(5), sequence: [
+        Http(Get(url: "/", name: "home")),
+    ]))]"#;
+    let messages = |contents: &str| -> Vec<String> {
+        lint_situation(&SituationSpec::test(contents).unwrap())
+            .into_iter()
+            .filter(|problem| problem.grunt.is_none() && !problem.message.contains("lua_file"))
+            .map(|problem| problem.message)
+            .collect()
+    };
+
+    assert!(messages(&format!(
+        r#"({}, arrival_rate: PerSecond(5), thresholds: [ErrorRate(max: 1, step: "home")])"#,
+        grunts
+    ))
+    .is_empty());
+
+    let problems = messages(&format!(
+        r#"({}, thresholds: [ErrorRate(max: 1, step: "nope")])"#,
+        grunts
+    ));
+    assert_eq!(1, problems.len(), "{:?}", problems);
+    assert!(problems[0].contains("\"nope\""), "{}", problems[0]);
+
+    let problems = messages(&format!(
+        r#"({}, arrival_rate: PerSecond(0), phases: [(duration: Seconds(0))])"#,
+        grunts
+    ));
+    assert_eq!(2, problems.len(), "{:?}", problems);
+    assert!(problems[0].contains("longer than zero"), "{}", problems[0]);
+    assert!(problems[1].contains("arrival_rate"), "{}", problems[1]);
+}
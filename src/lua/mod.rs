@@ -3,6 +3,9 @@ use rlua::{Context, Lua, RegistryKey, Result as LuaResult};
 use std::path::Path;
 use std::rc::Rc;
 
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use crate::pipe_contents::PipeContents;
 use crate::pipeline::step_handler::StepError;
 
@@ -17,9 +20,9 @@ pub struct LuaForPipeline {
 }
 
 impl LuaForPipeline {
-    pub fn new(user_script_path: &Path) -> LuaResult<Self> {
+    pub fn new(user_script_path: &Path, seed: u64) -> LuaResult<Self> {
         let lua = Lua::default();
-        attach_seatrial_stdlib(&lua)?;
+        attach_seatrial_stdlib(&lua, seed)?;
 
         let user_script_registry_key = attach_user_script(&lua, user_script_path)?;
 
@@ -37,6 +40,16 @@ impl LuaForPipeline {
         self.lua.context(callback)
     }
 
+    /// whether the user script's returned table has a function by this name, for use by
+    /// seatrial lint
+    pub fn has_user_script_function(&self, name: &str) -> bool {
+        self.context(|ctx| {
+            ctx.registry_value::<rlua::Table>(&self.user_script_registry_key)
+                .and_then(|user_script| user_script.get::<_, rlua::Value>(name))
+                .map_or(false, |val| matches!(val, rlua::Value::Function(_)))
+        })
+    }
+
     // TODO: this returning StepError is a holdover from when this function wasn't part of
     // LuaForPipeline and there was more spaghetti in the codebase. in this new keto world we have
     // less spaghetti, and this needs refactored
@@ -63,6 +76,27 @@ impl LuaForPipeline {
     }
 }
 
+// the fixture the tests of modules running Lua build their contexts from
+#[cfg(test)]
+impl LuaForPipeline {
+    /// a context whose user script is the given source, written out to (and then removed from)
+    /// the system's temporary directory, seeded with 0
+    pub fn test(script: &str) -> Self {
+        static SCRIPTS: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "seatrial_test_{}_{}.lua",
+            std::process::id(),
+            SCRIPTS.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&path, script).unwrap();
+        let lua = Self::new(&path, 0);
+        let _ = std::fs::remove_file(&path);
+
+        lua.unwrap()
+    }
+}
+
 fn attach_user_script(lua: &Lua, user_script_path: &Path) -> LuaResult<Rc<RegistryKey>> {
     let fpath = if let Some(parent) = user_script_path.parent() {
         let mut ret = parent.to_path_buf();
@@ -1,10 +1,13 @@
 use rlua::{Lua, Result as LuaResult};
 
+pub mod seed;
 pub mod validation_result;
 
+pub use seed::attach_seed;
 pub use validation_result::{attach_validationresult, ValidationResult};
 
-pub fn attach_seatrial_stdlib(lua: &Lua) -> LuaResult<()> {
+pub fn attach_seatrial_stdlib(lua: &Lua, seed: u64) -> LuaResult<()> {
+    attach_seed(lua, seed)?;
     attach_validationresult(lua)?;
     Ok(())
 }
@@ -0,0 +1,64 @@
+use rlua::{Lua, Result as LuaResult};
+
+#[cfg(test)]
+use crate::seed::derive_seed;
+
+/// seeds math.random with the grunt's seed (see seatrial --seed), and exposes that seed as the
+/// global Seed for user scripts wanting to seed generators of their own
+pub fn attach_seed(lua: &Lua, seed: u64) -> LuaResult<()> {
+    // derived seeds always fit, but it costs nothing to never wrap into a negative integer
+    let seed = (seed & i64::MAX as u64) as i64;
+
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+
+        globals
+            .get::<_, rlua::Table>("math")?
+            .get::<_, rlua::Function>("randomseed")?
+            .call::<_, ()>(seed)?;
+        globals.set("Seed", seed)?;
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+fn random_draws(seed: u64) -> LuaResult<Vec<i64>> {
+    let lua = Lua::default();
+    attach_seed(&lua, seed)?;
+
+    lua.context(|ctx| {
+        ctx.load("return { math.random(1000000), math.random(1000000), math.random(1000000) }")
+            .eval::<Vec<i64>>()
+    })
+}
+
+#[test]
+fn same_seed_same_draws() -> LuaResult<()> {
+    let seed = derive_seed(42, "Grunt 0");
+
+    assert_eq!(random_draws(seed)?, random_draws(seed)?);
+
+    Ok(())
+}
+
+#[test]
+fn different_seed_different_draws() -> LuaResult<()> {
+    assert_ne!(
+        random_draws(derive_seed(42, "Grunt 0"))?,
+        random_draws(derive_seed(42, "Grunt 1"))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn seed_is_exposed() -> LuaResult<()> {
+    let lua = Lua::default();
+    attach_seed(&lua, 1234)?;
+
+    lua.context(|ctx| {
+        assert_eq!(1234, ctx.globals().get::<_, i64>("Seed")?);
+        Ok(())
+    })
+}
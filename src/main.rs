@@ -1,48 +1,126 @@
-use url::Url;
-
-use std::str::FromStr;
-use std::sync::mpsc::channel;
-use std::sync::{Arc, Barrier};
+use nanoserde::SerJson;
+
+use std::fs::File;
+use std::io::Write;
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, sync_channel, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Barrier, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
+mod arrival_rate;
 mod cli;
 mod combinator;
+mod compare;
 mod config_duration;
+mod events;
+mod executor;
 mod grunt;
+mod histogram;
 mod http;
 mod http_response_table;
+mod interpolation;
+mod junit;
+mod library;
+mod lint;
 mod lua;
+mod outcome;
 mod persona;
+mod phase;
 mod pipe_contents;
 mod pipeline;
+mod progress;
+mod prometheus;
+mod ramp_up;
+mod report;
+mod seed;
+mod shutdown;
 mod situation;
+mod sleep_distribution;
+mod statsd;
+mod summary;
+mod targets;
+mod threshold;
+mod timeseries;
 mod validator;
-
-use crate::cli::parse_args;
-use crate::grunt::Grunt;
-use crate::lua::LuaForPipeline;
-use crate::pipeline::step_handler::{StepError, StepHandlerInitError};
-use crate::pipeline::{Pipeline, PipelineStepResult};
+mod warnings;
+
+use crate::cli::{parse_args, CmdArgs, Command};
+use crate::compare::compare_reports;
+use crate::events::{EventLog, RunEvent};
+use crate::executor::{load_lua, run_pass, worker, Assignment, PassEnd};
+use crate::junit::JunitReport;
+use crate::lint::lint_situations;
+use crate::outcome::Outcome;
+use crate::pipeline::step_handler::StepHandlerInitError;
+use crate::pipeline::Pipeline;
+use crate::progress::Progress;
+use crate::prometheus::Metrics;
+use crate::report::RunReport;
+use crate::shutdown::{install_signal_handlers, StopCondition};
 use crate::situation::Situation;
+use crate::statsd::StatsdEmitter;
+use crate::summary::RunSummary;
+use crate::targets::parse_base_url;
+use crate::threshold::Verdict;
+use crate::timeseries::TimeSeries;
+use crate::warnings::WarningLog;
+
+/// how many worker threads grunts are run on when --workers isn't given, at most. workers spend
+/// most of their time blocked on HTTP responses rather than on a CPU, so this isn't derived from
+/// the core count; it's merely low enough to keep a heavily multiplied run from spawning a thread
+/// per grunt, and high enough that smaller runs still get one
+const DEFAULT_MAX_WORKERS: usize = 256;
 
 fn main() -> std::io::Result<()> {
-    let args = parse_args();
-
-    // TODO: no unwrap, which will also kill the nasty parens
-    let base_url = (if args.base_url.ends_with('/') {
-        Url::from_str(&args.base_url)
-    } else {
-        Url::from_str(&format!("{}/", args.base_url))
-    })
-    .unwrap();
-
-    // TODO: get rid of unwrap!
-    let situations: Vec<Situation> = args
-        .situations
-        .iter()
-        .map(|situation| Situation::from_spec(situation, &base_url, args.multiplier).unwrap())
-        .collect();
+    let outcome = match parse_args() {
+        Command::Run(args) => run(*args)?,
+        Command::Lint(args) => {
+            if lint_situations(&args) > 0 {
+                Outcome::ConfigFailure
+            } else {
+                Outcome::Success
+            }
+        }
+        Command::Compare(args) => report_outcome(compare_reports(&args)),
+    };
+
+    std::process::exit(outcome.exit_status());
+}
+
+fn run(args: CmdArgs) -> std::io::Result<Outcome> {
+    let base_url = match parse_base_url(&args.base_url) {
+        Ok(base_url) => base_url,
+        Err(err) => {
+            eprintln!("[seatrial] invalid base URL {}: {}", args.base_url, err);
+            return Ok(Outcome::ConfigFailure);
+        }
+    };
+
+    // printed no matter where it came from, such that any run can be replayed with the same
+    // generated data
+    let seed = args.seed.unwrap_or_else(rand::random);
+    eprintln!("[seatrial] seed: {}", seed);
+
+    let mut situations: Vec<Situation> = Vec::with_capacity(args.situations.len());
+    for spec in &args.situations {
+        match Situation::from_spec(
+            spec,
+            &base_url,
+            &args.targets,
+            args.multiplier,
+            args.duration.as_ref(),
+            seed,
+        ) {
+            Ok(situation) => situations.push(situation),
+            Err(err) => {
+                eprintln!("[{}] {}", spec.name(), err.message());
+                return Ok(Outcome::ConfigFailure);
+            }
+        }
+    }
 
     // TODO: find a less hacky way of dealing with situation lifecycles. this is a brute-force
     // "just throw it on the heap until the kernel kills the process when we exit" hackaround
@@ -50,247 +128,486 @@ fn main() -> std::io::Result<()> {
     // land, which _works_, but feels messy
     let situations = Box::new(situations).leak();
 
-    // no need for any of the ephemeral *Spec objects at this point
-    drop(args);
+    if args.dry_run {
+        return Ok(report_outcome(dry_run(situations).unwrap_or_else(|_| {
+            eprintln!("[seatrial] dry run failed to initialize step handlers");
+            Outcome::InternalFailure
+        })));
+    }
 
-    let (sit_tx, sit_rx) = channel::<Result<(), StepHandlerInitError>>();
-    let barrier = Arc::new(Barrier::new(situations.len()));
-    let situation_threads = situations
-        .iter()
-        .map(|situation| {
-            let sit_tx = sit_tx.clone();
-            let barrier = barrier.clone();
+    let progress_interval = args.progress.as_ref().map(Duration::from);
+    if progress_interval == Some(Duration::ZERO) {
+        eprintln!("[seatrial] --progress interval must be greater than zero");
+        return Ok(Outcome::ConfigFailure);
+    }
 
-            thread::spawn(move || {
-                let (grunt_tx, grunt_rx) = channel::<Result<(), StepHandlerInitError>>();
-
-                let grunt_threads: Vec<_> = situation
-                    .grunts
-                    .iter()
-                    .map(|grunt| {
-                        let grunt_tx = grunt_tx.clone();
-                        let barrier = barrier.clone();
-                        let situation = situation.clone();
-                        thread::spawn(move || {
-                            grunt_tx
-                                .send(grunt_worker(barrier, &situation, grunt))
-                                .unwrap()
-                        })
-                    })
-                    .collect();
-
-                drop(grunt_tx);
-
-                for thread in grunt_threads {
-                    match grunt_rx.recv().unwrap() {
-                        Ok(_) => {
-                            // there's no actual guarantee here that thread is the same thread taht
-                            // sent whatever data we got, and that's okay, we're using this as a
-                            // lazy place to just ensure all threads have finished, not as a proper
-                            // data consolidation step
-                            thread.join().unwrap();
-                            sit_tx.send(Ok(()))
-                        }
-                        res @ Err(_) => sit_tx.send(res),
-                    }
-                    .ok();
-                }
-            })
-        })
-        .collect::<Vec<JoinHandle<_>>>();
-
-    drop(sit_tx);
-
-    for thread in situation_threads {
-        match sit_rx.recv() {
-            Ok(_) => {
-                // there's no actual guarantee here that thread is the same thread taht sent
-                // whatever data we got, and that's okay, we're using this as a lazy place to just
-                // ensure all threads have finished, not as a proper data consolidation step
-                thread.join().unwrap();
-                Ok(())
+    let mut event_log = match &args.events {
+        None => None,
+        Some(path) => match EventLog::create(path) {
+            Ok(log) => Some(log),
+            Err(err) => {
+                eprintln!(
+                    "[seatrial] could not open events file {}: {}",
+                    path.display(),
+                    err
+                );
+                return Ok(Outcome::ConfigFailure);
             }
-            Err(err) => Err(err),
-        }
-        .expect("internal error: situation thread did not join");
-    }
+        },
+    };
+
+    // created up front, such that a bad path is caught before any traffic is sent, but only
+    // written to once the run is over
+    let mut junit = match &args.junit {
+        None => None,
+        Some(path) => match File::create(path) {
+            Ok(file) => Some((file, JunitReport::new(situations))),
+            Err(err) => {
+                eprintln!(
+                    "[seatrial] could not open JUnit report file {}: {}",
+                    path.display(),
+                    err
+                );
+                return Ok(Outcome::ConfigFailure);
+            }
+        },
+    };
 
-    Ok(())
-}
+    let mut report_file = match &args.report {
+        None => None,
+        Some(path) => match File::create(path) {
+            Ok(file) => Some(file),
+            Err(err) => {
+                eprintln!(
+                    "[seatrial] could not open report file {}: {}",
+                    path.display(),
+                    err
+                );
+                return Ok(Outcome::ConfigFailure);
+            }
+        },
+    };
 
-fn grunt_worker(
-    barrier: Arc<Barrier>,
-    situation: &Situation,
-    grunt: &Grunt,
-) -> Result<(), StepHandlerInitError> {
-    if situation.lua_file.is_none() {
-        unimplemented!("situations without 'lua_file' are not currently supported");
+    let timeseries_window = Duration::from(&args.timeseries_window);
+    if timeseries_window.is_zero() {
+        eprintln!("[seatrial] --timeseries-window must be greater than zero");
+        return Ok(Outcome::ConfigFailure);
     }
 
-    // TODO: no final unwrap. lua_file.unwrap() is guarded above.
-    let lua = LuaForPipeline::new(situation.lua_file.as_ref().unwrap()).unwrap_or_else(
-        |err: rlua::Error| {
-            eprintln!("[{}] aborting due to lua error", grunt.name);
-            eprintln!("[{}] err was: {}", grunt.name, err);
-            panic!();
+    let mut timeseries = match &args.timeseries {
+        None => None,
+        Some(path) => match File::create(path) {
+            Ok(file) => Some((
+                file,
+                TimeSeries::new(timeseries_window),
+                args.timeseries_format,
+            )),
+            Err(err) => {
+                eprintln!(
+                    "[seatrial] could not open time series file {}: {}",
+                    path.display(),
+                    err
+                );
+                return Ok(Outcome::ConfigFailure);
+            }
         },
-    );
+    };
+
+    // bound up front, such that an address already in use is caught before any traffic is sent
+    let metrics = match args.metrics_listen {
+        None => None,
+        Some(addr) => match TcpListener::bind(addr) {
+            Ok(listener) => {
+                let metrics = Arc::new(Mutex::new(Metrics::default()));
+                let served = metrics.clone();
+                thread::spawn(move || prometheus::serve(listener, served));
+                eprintln!("[seatrial] serving metrics at http://{}/metrics", addr);
+                Some(metrics)
+            }
+            Err(err) => {
+                eprintln!(
+                    "[seatrial] could not listen for metrics on {}: {}",
+                    addr, err
+                );
+                return Ok(Outcome::ConfigFailure);
+            }
+        },
+    };
 
-    barrier.wait();
+    let mut statsd = match &args.statsd {
+        None => None,
+        Some(addr) => match StatsdEmitter::connect(addr, &args.statsd_prefix, args.dogstatsd) {
+            Ok(statsd) => Some(statsd),
+            Err(err) => {
+                eprintln!(
+                    "[seatrial] could not send StatsD metrics to {}: {}",
+                    addr, err
+                );
+                return Ok(Outcome::ConfigFailure);
+            }
+        },
+    };
+    let mut statsd_failed = false;
+
+    // there's no use in more workers than there are grunts to give them, nor, by default, in
+    // spawning a thread per grunt when a run is multiplied into the thousands of them
+    let grunt_count: usize = situations
+        .iter()
+        .map(|situation| situation.grunts.len())
+        .sum();
+    let worker_count = args
+        .workers
+        .unwrap_or_else(|| grunt_count.min(DEFAULT_MAX_WORKERS))
+        .clamp(1, grunt_count.max(1));
+
+    // grunts sharing a worker take turns, and each worker's HTTP requests block it until they're
+    // answered, so fewer workers than grunts means fewer requests in flight than there are
+    // simulated users, which shouldn't go unmentioned
+    if worker_count < grunt_count {
+        eprintln!(
+            "[seatrial] running {} grunts on {} workers, which they take turns on: requests in flight are capped at {} (see --workers)",
+            grunt_count, worker_count, worker_count
+        );
+    }
 
-    for step_result in Pipeline::new(grunt, &situation.base_url, Some(&lua))? {
-        match step_result {
-            Ok(PipelineStepResult::Ok) => {}
+    let warning_rate = args.warning_rate;
+
+    // no need for any of the ephemeral *Spec objects at this point
+    drop(args);
 
-            Ok(PipelineStepResult::OkWithWarnings(warnings)) => {
-                // TODO: in addition to printing, we need to track structured events (not just
-                // for these warnings, but for all sorts of pipeline actions)
+    // the first interrupt asks grunts to finish their current step and wrap up; a second one
+    // exits immediately
+    let interrupted = install_signal_handlers()?;
+
+    // open-model situations hand out persona iterations to whichever of their grunts is idle;
+    // the buffer allows a grunt's worth of backlog before arrivals get dropped
+    let mut schedules: Vec<(&'static Situation, SyncSender<()>)> = Vec::new();
+    let mut assignments: Vec<Assignment> = Vec::new();
+
+    for situation in situations.iter() {
+        let arrivals = situation.arrival_interval.map(|_| {
+            let (tickets, arrivals) = sync_channel::<()>(situation.grunts.len());
+            schedules.push((situation, tickets));
+            Arc::new(Mutex::new(arrivals))
+        });
+
+        assignments.extend(situation.grunts.iter().map(|grunt| Assignment {
+            situation,
+            grunt,
+            arrivals: arrivals.clone(),
+        }));
+    }
 
-                for warning in warnings {
-                    eprintln!(
-                        "[{}] warning issued during pipeline step completion: {}",
-                        grunt.name, warning
-                    );
+    // grunts are dealt out to workers like cards, such that each situation's grunts are spread
+    // as evenly as possible across the pool
+    let mut worker_assignments: Vec<Vec<Assignment>> = vec![Vec::new(); worker_count];
+    for (idx, assignment) in assignments.into_iter().enumerate() {
+        worker_assignments[idx % worker_count].push(assignment);
+    }
+
+    // every worker (plus the arrival scheduler of each open-model situation) waits here until
+    // all Lua contexts are loaded, such that ramp-up delays and arrival schedules are measured
+    // from a common starting line
+    let barrier = Arc::new(Barrier::new(worker_count + schedules.len()));
+
+    // every step taken by every grunt (and every grunt starting and stopping) is reported back
+    // here, to the main thread. the channel is unbounded, such that a main thread which falls
+    // behind costs memory rather than holding up grunts mid-run
+    let (events, recorded) = channel::<RunEvent>();
+
+    let workers: Vec<JoinHandle<_>> = worker_assignments
+        .into_iter()
+        .map(|assignments| {
+            let barrier = barrier.clone();
+            let interrupted = interrupted.clone();
+            let events = events.clone();
+            thread::spawn(move || worker(barrier, assignments, interrupted, events))
+        })
+        .collect();
+
+    // such that the loop below ends once the last worker does
+    drop(events);
+
+    let schedulers: Vec<(&Situation, JoinHandle<_>)> = schedules
+        .into_iter()
+        .map(|(situation, tickets)| {
+            let barrier = barrier.clone();
+            let interrupted = interrupted.clone();
+            let scheduled =
+                thread::spawn(move || schedule_arrivals(&barrier, situation, tickets, interrupted));
+            (situation, scheduled)
+        })
+        .collect();
+
+    let mut outcome = Outcome::Success;
+    let mut summary = RunSummary::default();
+    let mut warnings = WarningLog::new(warning_rate);
+
+    // workers start the clock once they're past the barrier, which is close enough to now for
+    // the purposes of progress lines
+    let mut progress = progress_interval.map(|interval| {
+        let started = Instant::now();
+        let progress = Progress::new(situations.iter().map(|it| it.name.as_str()), started);
+        (progress, started + interval)
+    });
+
+    loop {
+        let event = match &mut progress {
+            None => match recorded.recv() {
+                Ok(event) => event,
+                Err(_) => break,
+            },
+            Some((progress, next_report)) => {
+                // reports are due on time whether or not events stop coming in for long enough
+                // to wait one out, which under steady load they never do. should the loop have
+                // fallen behind by more than an interval, one report covers all of it
+                let now = Instant::now();
+                if now >= *next_report {
+                    eprint!("{}", progress.report(now));
+                    // progress_interval is Some whenever progress is
+                    while *next_report <= now {
+                        *next_report += progress_interval.unwrap();
+                    }
                 }
-            }
 
-            Ok(PipelineStepResult::OkWithExit) => {
-                break;
+                match recorded.recv_timeout(next_report.saturating_duration_since(now)) {
+                    Ok(event) => {
+                        progress.record(&event);
+                        event
+                    }
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
             }
+        };
 
-            Err(err) => {
-                process_step_error(grunt, err);
-                break;
-            }
+        if let Some(metrics) = &metrics {
+            metrics
+                .lock()
+                .expect("internal error: metrics lock poisoned")
+                .record(&event);
         }
-    }
 
-    grunt_exit(grunt);
+        if let Some(statsd) = &mut statsd {
+            // a StatsD agent coming and going is no reason to fail a run, nor to drown out
+            // everything else in errors
+            if let Err(err) = statsd.record(&event) {
+                if !statsd_failed {
+                    eprintln!(
+                        "[seatrial] error sending StatsD metrics, no more errors will be reported: {}",
+                        err
+                    );
+                    statsd_failed = true;
+                }
+            }
+        }
 
-    Ok(())
-}
+        let event = match event {
+            RunEvent::Step(event) => event,
+            RunEvent::GruntStarted(_) | RunEvent::GruntStopped(_) => continue,
+        };
 
-fn grunt_exit(grunt: &Grunt) {
-    eprintln!("[{}] reached end of pipeline, goodbye!", grunt.name);
-}
+        summary.record(&event);
 
-fn process_step_error(grunt: &Grunt, err: StepError) {
-    match err {
-        StepError::Unclassified => {
-            eprintln!(
-                "[{}] aborting due to unclassified error in pipeline",
-                grunt.name
-            );
-            eprintln!(
-                "[{}] this is an error in seatrial - TODO fix this",
-                grunt.name
-            );
-            // TODO: restore
-            //eprintln!("[{}] step was: {:?}", grunt.name, step);
+        for warning in warnings.record(&event, Instant::now()) {
+            eprintln!("{}", warning);
         }
 
-        StepError::Validation(err) => {
-            eprintln!(
-                "[{}] aborting due to validation error in pipeline",
-                grunt.name
-            );
-            eprintln!("[{}] err was: {}", grunt.name, err);
-            // TODO: restore
-            //eprintln!("[{}] step was: {:?}", grunt.name, step);
+        if let Some((_, report)) = &mut junit {
+            report.record(&event);
         }
 
-        // TODO: more details - we're just not plumbing the details around
-        StepError::ValidationSucceededUnexpectedly => {
-            eprintln!(
-                "[{}] aborting because a validation succeeded where we expected a failure",
-                grunt.name
-            );
-            eprintln!(
-                "[{}] this is an error in seatrial - TODO fix this",
-                grunt.name
-            );
-            // TODO: restore
-            //eprintln!("[{}] step was: {:?}", grunt.name, step);
+        if let Some((_, series, _)) = &mut timeseries {
+            series.record(&event);
         }
 
-        StepError::InvalidActionInContext => {
-            eprintln!(
-                "[{}] aborting due to invalid action definition in the given context",
-                grunt.name
-            );
-            eprintln!(
-                "[{}] that this was not caught in a linter run is an error in seatrial - TODO fix this",
-                grunt.name
-            );
-            // TODO: restore
-            //eprintln!("[{}] step was: {:?}", grunt.name, step);
+        if let Some(log) = &mut event_log {
+            if let Err(err) = log.record(&event) {
+                eprintln!(
+                    "[seatrial] error writing events, no more will be written: {}",
+                    err
+                );
+                event_log = None;
+                outcome = outcome.worst(Outcome::InternalFailure);
+            }
         }
+    }
 
-        StepError::IO(err) => {
-            eprintln!("[{}] aborting due to internal IO error", grunt.name);
-            eprintln!("[{}] err was: {}", grunt.name, err);
-            // TODO: restore
-            //eprintln!("[{}] step was: {:?}", grunt.name, step);
-        }
+    if let Some(Err(err)) = event_log.map(EventLog::finish) {
+        eprintln!("[seatrial] error writing events: {}", err);
+        outcome = outcome.worst(Outcome::InternalFailure);
+    }
 
-        StepError::LuaException(err) => {
-            eprintln!("[{}] aborting due to lua error", grunt.name);
-            eprintln!("[{}] err was: {}", grunt.name, err);
-            // TODO: restore
-            //eprintln!("[{}] step was: {:?}", grunt.name, step);
+    if let Some((mut file, report)) = junit {
+        if let Err(err) = file.write_all(report.render().as_bytes()) {
+            eprintln!("[seatrial] error writing JUnit report: {}", err);
+            outcome = outcome.worst(Outcome::InternalFailure);
         }
+    }
 
-        StepError::UrlParsing(err) => {
-            eprintln!("[{}] aborting due to url parsing error", grunt.name);
-            eprintln!("[{}] err was: {}", grunt.name, err);
-            // TODO: restore
-            //eprintln!("[{}] step was: {:?}", grunt.name, step);
+    if let Some((mut file, series, format)) = timeseries {
+        if let Err(err) = file.write_all(series.render(format).as_bytes()) {
+            eprintln!("[seatrial] error writing time series: {}", err);
+            outcome = outcome.worst(Outcome::InternalFailure);
         }
+    }
 
-        StepError::Http(err) => {
-            eprintln!("[{}] aborting due to http error", grunt.name);
-            eprintln!("[{}] err was: {}", grunt.name, err);
-            // TODO: restore
-            //eprintln!("[{}] step was: {:?}", grunt.name, step);
+    for (situation, scheduler) in schedulers {
+        match scheduler.join() {
+            Ok(dropped) => summary.record_dropped_arrivals(&situation.name, dropped),
+            Err(_) => outcome = outcome.worst(Outcome::InternalFailure),
         }
+    }
+
+    for worker in workers {
+        outcome = outcome.worst(match worker.join() {
+            Ok(Ok(worker_outcome)) => worker_outcome,
+            Ok(Err(_)) | Err(_) => Outcome::InternalFailure,
+        });
+    }
+
+    if interrupted.load(Ordering::Relaxed) {
+        eprintln!("[seatrial] run was interrupted before completion");
 
-        StepError::RefuseToStringifyComplexLuaValue => {
-            eprintln!(
-                "[{}] aborting attempt to stringify complex lua value",
-                grunt.name
-            );
-            // TODO: restore
-            //eprintln!("[{}] step was: {:?}", grunt.name, step);
+        // even if every grunt managed to wrap up cleanly, the run didn't do everything it was
+        // asked to do
+        outcome = outcome.worst(Outcome::Interrupted);
+    }
+
+    if !summary.is_empty() {
+        println!(
+            "[seatrial] summary of {:.1}s run (latencies in ms)",
+            summary.elapsed().as_secs_f64()
+        );
+        print!("{}", summary.render());
+    }
+
+    if let Some(started) = summary.started().filter(|_| !warnings.is_empty()) {
+        println!("[seatrial] warnings (times since the start of the run)");
+        print!("{}", warnings.render(started));
+    }
+
+    if let Some(file) = &mut report_file {
+        let report = RunReport::new(&summary, seed);
+        if let Err(err) = writeln!(file, "{}", report.serialize_json()) {
+            eprintln!("[seatrial] error writing report: {}", err);
+            outcome = outcome.worst(Outcome::InternalFailure);
         }
+    }
 
-        StepError::RefuseToStringifyNonExistantValue => {
-            eprintln!(
-                "[{}] aborting attempt to stringify non-existent (probably nil) lua value",
-                grunt.name
-            );
-            // TODO: restore
-            //eprintln!("[{}] step was: {:?}", grunt.name, step);
+    let verdicts: Vec<Verdict> = situations
+        .iter()
+        .flat_map(|situation| {
+            let summary = &summary;
+            let arrivals = situation.arrival_interval.map(|_| {
+                Verdict::arrivals(&situation.name, summary.dropped_arrivals(&situation.name))
+            });
+
+            situation
+                .thresholds
+                .iter()
+                .map(move |threshold| {
+                    threshold.evaluate(
+                        &situation.name,
+                        &summary.stats(&situation.name, threshold.step()),
+                        summary.elapsed(),
+                    )
+                })
+                .chain(arrivals)
+        })
+        .collect();
+
+    if !verdicts.is_empty() {
+        println!("[seatrial] thresholds");
+        print!("{}", threshold::render(&verdicts));
+
+        if verdicts.iter().any(|verdict| !verdict.passed) {
+            outcome = outcome.worst(Outcome::ThresholdFailure);
         }
+    }
+
+    Ok(report_outcome(outcome))
+}
 
-        // TODO: FIXME this messaging is extremely hard to grok, I'd be pounding my head
-        // into the keyboard screaming obscenities if a tool offered me this as the sole
-        // debug output
-        StepError::RequestedLuaValueWhereNoneExists => {
-            eprintln!(
-                "[{}] aborting attempt to pass non-existent value to lua context",
-                grunt.name
-            );
-            // TODO: restore
-            //eprintln!("[{}] step was: {:?}", grunt.name, step);
+fn report_outcome(outcome: Outcome) -> Outcome {
+    if outcome != Outcome::Success {
+        eprintln!(
+            "[seatrial] exiting with status {}: {}",
+            outcome.exit_status(),
+            outcome.description()
+        );
+    }
+
+    outcome
+}
+
+// walks each grunt's sequence once, one grunt at a time, printing what would be sent rather than
+// sending it
+fn dry_run(situations: &[Situation]) -> Result<Outcome, StepHandlerInitError> {
+    let mut outcome = Outcome::Success;
+
+    for situation in situations {
+        for grunt in &situation.grunts {
+            let lua = match load_lua(situation, grunt) {
+                Some(lua) => lua,
+                None => {
+                    outcome = outcome.worst(Outcome::LuaFailure);
+                    continue;
+                }
+            };
+            let mut pipeline = Pipeline::new(grunt, &situation.targets, Some(&lua), true, None)?;
+
+            if let PassEnd::Failed(failure) = run_pass(
+                grunt,
+                &mut pipeline,
+                &StopCondition::new(None, Arc::default()),
+            ) {
+                outcome = outcome.worst(failure);
+            }
         }
+    }
 
-        StepError::LuaNotInstantiated => {
-            eprintln!(
-                "[{}] aborting attempt to use lua when it is not instantiated",
-                grunt.name
-            );
-            // TODO: restore
-            //eprintln!("[{}] step was: {:?}", grunt.name, step);
+    Ok(outcome)
+}
+
+// hands out one persona iteration per arrival_interval to whichever grunt is idle, until the
+// situation's duration runs out. arrivals are never queued beyond the channel's buffer: if every
+// grunt is busy and the backlog is full, the arrival is dropped rather than delayed, and the
+// number dropped is returned
+fn schedule_arrivals(
+    barrier: &Barrier,
+    situation: &Situation,
+    tickets: SyncSender<()>,
+    interrupted: Arc<AtomicBool>,
+) -> u64 {
+    let interval = situation
+        .arrival_interval
+        .expect("internal error: scheduling arrivals for a closed-model situation");
+    let duration = situation
+        .duration
+        .expect("internal error: open-model situation without a duration");
+
+    barrier.wait();
+
+    let started = Instant::now();
+    let stop = StopCondition::new(Some(started + duration), interrupted);
+    let mut next_arrival = started;
+    let mut dropped: u64 = 0;
+
+    // sleeping checks for a reason to stop before anything else, even when there's no time to
+    // wait before the next arrival
+    while stop
+        .sleep(next_arrival.saturating_duration_since(Instant::now()))
+        .is_none()
+    {
+        match tickets.try_send(()) {
+            Ok(()) => {}
+            Err(TrySendError::Full(())) => dropped += 1,
+            Err(TrySendError::Disconnected(())) => break,
         }
+
+        next_arrival += interval;
     }
+
+    dropped
 }
@@ -0,0 +1,141 @@
+use crate::pipeline::step_handler::StepError;
+
+/// how a run (or any part of one: a situation, a grunt, a single pass through a pipeline) turned
+/// out. variants are ordered from best to worst, such that the outcome of a whole is the worst
+/// outcome among its parts
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Outcome {
+    Success,
+
+    // the run was cut short by a signal, but nothing actually failed before then
+    Interrupted,
+
+    // every step went fine, but the run as a whole didn't meet a situation's thresholds (or,
+    // for seatrial compare, regressed from its baseline beyond the given tolerances)
+    ThresholdFailure,
+
+    // a validator (or combinator) failed
+    ValidationFailure,
+
+    // an HTTP request failed at the transport level (timeouts, refused connections, and the like)
+    TransportFailure,
+
+    // the Lua user script failed to load, raised an error, or returned something unusable
+    LuaFailure,
+
+    // the situation config (or command line) asked for something that can't be done
+    ConfigFailure,
+
+    // something went wrong inside seatrial itself
+    InternalFailure,
+}
+
+impl Outcome {
+    pub fn worst(self, other: Self) -> Self {
+        self.max(other)
+    }
+
+    pub fn exit_status(self) -> i32 {
+        match self {
+            Self::Success => 0,
+            Self::ConfigFailure => 1,
+            Self::InternalFailure => 2,
+            Self::ValidationFailure => 3,
+            Self::TransportFailure => 4,
+            Self::LuaFailure => 5,
+            Self::ThresholdFailure => 6,
+
+            // per the shell convention of 128 + SIGINT, and matching a forced exit on a second
+            // signal
+            Self::Interrupted => 130,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Interrupted => "run was interrupted",
+            Self::ThresholdFailure => "at least one threshold was not met",
+            Self::ValidationFailure => "at least one validation failed",
+            Self::TransportFailure => "at least one HTTP request failed at the transport level",
+            Self::LuaFailure => "at least one Lua error occurred",
+            Self::ConfigFailure => "configuration error",
+            Self::InternalFailure => "internal error in seatrial",
+        }
+    }
+}
+
+impl From<&StepError> for Outcome {
+    fn from(err: &StepError) -> Self {
+        match err {
+            StepError::Validation(_) | StepError::ValidationSucceededUnexpectedly => {
+                Self::ValidationFailure
+            }
+
+            StepError::Http(_) | StepError::IO(_) => Self::TransportFailure,
+
+            StepError::LuaException(_)
+            | StepError::RefuseToStringifyComplexLuaValue
+            | StepError::RefuseToStringifyNonExistantValue
+            | StepError::RequestedLuaValueWhereNoneExists => Self::LuaFailure,
+
+            StepError::InvalidActionInContext
+            | StepError::LuaNotInstantiated
+            | StepError::UrlParsing(_) => Self::ConfigFailure,
+
+            StepError::Unclassified => Self::InternalFailure,
+        }
+    }
+}
+
+#[test]
+fn test_worst() {
+    assert_eq!(
+        Outcome::TransportFailure,
+        Outcome::ValidationFailure.worst(Outcome::TransportFailure)
+    );
+    assert_eq!(
+        Outcome::ValidationFailure,
+        Outcome::ValidationFailure.worst(Outcome::Success)
+    );
+    assert_eq!(
+        Outcome::ValidationFailure,
+        Outcome::Interrupted.worst(Outcome::ValidationFailure)
+    );
+}
+
+#[test]
+fn test_from_step_error() {
+    assert_eq!(
+        Outcome::ValidationFailure,
+        (&StepError::Validation("nope".into())).into()
+    );
+    assert_eq!(
+        Outcome::ConfigFailure,
+        (&StepError::InvalidActionInContext).into()
+    );
+    assert_eq!(
+        Outcome::LuaFailure,
+        (&StepError::RequestedLuaValueWhereNoneExists).into()
+    );
+}
+
+#[test]
+fn test_distinct_exit_statuses() {
+    let outcomes = [
+        Outcome::Success,
+        Outcome::Interrupted,
+        Outcome::ThresholdFailure,
+        Outcome::ValidationFailure,
+        Outcome::TransportFailure,
+        Outcome::LuaFailure,
+        Outcome::ConfigFailure,
+        Outcome::InternalFailure,
+    ];
+
+    for (idx, outcome) in outcomes.iter().enumerate() {
+        for other in &outcomes[idx + 1..] {
+            assert_ne!(outcome.exit_status(), other.exit_status());
+        }
+    }
+}
@@ -10,13 +10,24 @@ pub struct Persona {
     pub timeout: ConfigDuration,
     pub headers: HashMap<String, String>,
     pub sequence: Vec<PipelineAction>,
+
+    // what each step of the sequence goes by (see PipelineAction::name)
+    pub step_names: Vec<String>,
 }
 
 impl From<&PersonaSpec> for Persona {
     fn from(spec: &PersonaSpec) -> Self {
+        let (sequence, step_names) = spec
+            .sequence
+            .iter()
+            .enumerate()
+            .map(|(idx, step)| (step.unnamed().clone(), step.name(idx)))
+            .unzip();
+
         Self {
             timeout: spec.timeout.clone(),
-            sequence: spec.sequence.clone(),
+            sequence,
+            step_names,
 
             // TODO: populate with Value/LuaFunction returns, error on other PipelineAction
             // variants
@@ -31,3 +42,40 @@ pub struct PersonaSpec {
     pub headers: Option<ConfigActionMap>,
     pub sequence: Vec<PipelineAction>,
 }
+
+#[cfg(test)]
+fn persona(sequence: &str) -> Persona {
+    let spec: PersonaSpec =
+        DeRon::deserialize_ron(&format!("(timeout: Seconds(5), sequence: [{}])", sequence))
+            .unwrap();
+
+    Persona::from(&spec)
+}
+
+#[test]
+fn test_named_steps_are_unwrapped() {
+    let persona = persona(r#"Named("pick item", LuaFunction("pick")), LuaFunction("browse")"#);
+
+    assert_eq!(vec!["pick item", "browse"], persona.step_names);
+    assert!(matches!(
+        persona.sequence.as_slice(),
+        [PipelineAction::LuaFunction(pick), PipelineAction::LuaFunction(_)] if pick == "pick"
+    ));
+}
+
+#[test]
+fn test_default_step_names() {
+    let persona = persona(
+        r#"
+            Http(Get(url: "items/${id}")),
+            Http(Post(url: "items", name: "create item")),
+            LuaFunction("pick"),
+            Combinator(AnyOf([AssertStatusCode(200), AssertStatusCode(204)])),
+        "#,
+    );
+
+    assert_eq!(
+        vec!["GET items/${id}", "create item", "pick", "Combinator #3"],
+        persona.step_names
+    );
+}
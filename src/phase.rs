@@ -0,0 +1,251 @@
+use nanoserde::DeRon;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::config_duration::ConfigDuration;
+use crate::grunt::GruntSpec;
+use crate::ramp_up::RampUp;
+
+#[cfg(test)]
+use crate::persona::PersonaSpec;
+
+/// one stretch of a situation's life (say, warmup, steady state, spike, or cooldown), during
+/// which a fixed number of each grunt is active
+#[derive(Clone, Debug, DeRon)]
+pub struct PhaseSpec {
+    pub name: Option<String>,
+    pub duration: ConfigDuration,
+
+    // scales every grunt's count for the length of this phase (default 1)
+    pub multiplier: Option<usize>,
+
+    // overrides the count (and multiplier) of the grunts named here, by base_name
+    pub counts: Option<HashMap<String, usize>>,
+}
+
+impl PhaseSpec {
+    /// how many of the given grunt are active during this phase, before the command line
+    /// multiplier is applied
+    pub fn count_for(&self, grunt: &GruntSpec) -> usize {
+        self.counts
+            .as_ref()
+            .and_then(|counts| counts.get(&grunt.display_name()))
+            .copied()
+            .unwrap_or_else(|| grunt.real_count() * self.multiplier.unwrap_or(1))
+    }
+}
+
+/// a phase with its boundaries pinned down relative to the start of the run, and the number of
+/// each of the situation's grunts (in the order of their specs) active during it
+#[derive(Clone, Debug)]
+pub struct Phase {
+    pub start: Duration,
+    pub end: Option<Duration>,
+    pub counts: Vec<usize>,
+}
+
+/// a stretch of time, relative to the start of the run, during which a grunt should be walking
+/// its persona. a grunt with no end keeps going until its situation's duration (if any) elapses
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ActiveWindow {
+    pub start: Duration,
+    pub end: Option<Duration>,
+}
+
+/// works out when each copy of each grunt is active across the given phases, returning windows
+/// indexed by grunt spec, then by copy. a copy active in consecutive phases stays active across
+/// the boundary rather than restarting. copies joining in a phase are ramped up from the start of
+/// that phase: by their own grunt's ramp-up if it has one, or else by the situation-wide one,
+/// spread across every such joiner
+pub fn plan_windows(
+    specs: &[GruntSpec],
+    situation_ramp: Option<&RampUp>,
+    phases: &[Phase],
+) -> Vec<Vec<Vec<ActiveWindow>>> {
+    let mut windows: Vec<Vec<Vec<ActiveWindow>>> = (0..specs.len())
+        .map(|idx| {
+            let copies = phases.iter().map(|phase| phase.counts[idx]).max();
+            vec![Vec::new(); copies.unwrap_or(0)]
+        })
+        .collect();
+    let mut active: Vec<usize> = vec![0; specs.len()];
+
+    for phase in phases {
+        let mut situation_ramped: Vec<(usize, usize)> = Vec::new();
+
+        for (idx, spec) in specs.iter().enumerate() {
+            let count = phase.counts[idx];
+
+            for copy in windows[idx].iter_mut().take(count.min(active[idx])) {
+                if let Some(window) = copy.last_mut() {
+                    window.end = phase.end;
+                }
+            }
+
+            let joiners = active[idx].min(count)..count;
+            match &spec.ramp_up {
+                Some(ramp) => {
+                    let total = joiners.len();
+                    for (slot, copy) in joiners.enumerate() {
+                        windows[idx][copy].push(ActiveWindow {
+                            start: phase.start + ramp.start_delay(slot, total),
+                            end: phase.end,
+                        });
+                    }
+                }
+                None => situation_ramped.extend(joiners.map(|copy| (idx, copy))),
+            }
+
+            active[idx] = count;
+        }
+
+        let total = situation_ramped.len();
+        for (slot, (idx, copy)) in situation_ramped.into_iter().enumerate() {
+            windows[idx][copy].push(ActiveWindow {
+                start: phase.start
+                    + situation_ramp.map_or(Duration::ZERO, |ramp| ramp.start_delay(slot, total)),
+                end: phase.end,
+            });
+        }
+    }
+
+    // a ramp-up longer than its phase leaves the last few joiners with nothing to do
+    for copies in windows.iter_mut() {
+        for copy in copies.iter_mut() {
+            copy.retain(|window| window.end.map_or(true, |end| window.start < end));
+        }
+    }
+
+    windows
+}
+
+#[cfg(test)]
+fn grunt_spec(base_name: &str, count: usize, ramp_up: Option<RampUp>) -> GruntSpec {
+    GruntSpec {
+        base_name: Some(base_name.into()),
+        persona: Some(PersonaSpec {
+            headers: None,
+            sequence: vec![],
+            timeout: ConfigDuration::Seconds(30),
+        }),
+        persona_name: None,
+        count: Some(count),
+        ramp_up,
+    }
+}
+
+#[cfg(test)]
+fn window(start: u64, end: Option<u64>) -> ActiveWindow {
+    ActiveWindow {
+        start: Duration::from_secs(start),
+        end: end.map(Duration::from_secs),
+    }
+}
+
+#[test]
+fn test_count_for() {
+    let phase = PhaseSpec {
+        name: None,
+        duration: ConfigDuration::Seconds(10),
+        multiplier: Some(3),
+        counts: Some(HashMap::from([("Spiky".into(), 50)])),
+    };
+
+    assert_eq!(6, phase.count_for(&grunt_spec("Steady", 2, None)));
+    assert_eq!(50, phase.count_for(&grunt_spec("Spiky", 2, None)));
+}
+
+#[test]
+fn test_single_unbounded_phase() {
+    let windows = plan_windows(
+        &[grunt_spec("G", 2, None)],
+        Some(&RampUp::Stagger(ConfigDuration::Seconds(1))),
+        &[Phase {
+            start: Duration::ZERO,
+            end: None,
+            counts: vec![2],
+        }],
+    );
+
+    assert_eq!(
+        vec![vec![vec![window(0, None)], vec![window(1, None)]]],
+        windows
+    );
+}
+
+#[test]
+fn test_grunts_join_and_retire_at_phase_boundaries() {
+    let windows = plan_windows(
+        &[grunt_spec("G", 1, None)],
+        None,
+        &[
+            Phase {
+                start: Duration::ZERO,
+                end: Some(Duration::from_secs(10)),
+                counts: vec![1],
+            },
+            Phase {
+                start: Duration::from_secs(10),
+                end: Some(Duration::from_secs(20)),
+                counts: vec![3],
+            },
+            Phase {
+                start: Duration::from_secs(20),
+                end: Some(Duration::from_secs(30)),
+                counts: vec![0],
+            },
+            Phase {
+                start: Duration::from_secs(30),
+                end: Some(Duration::from_secs(40)),
+                counts: vec![2],
+            },
+        ],
+    );
+
+    assert_eq!(
+        vec![vec![
+            // active straight through the first two phases, then back for the last
+            vec![window(0, Some(20)), window(30, Some(40))],
+            vec![window(10, Some(20)), window(30, Some(40))],
+            vec![window(10, Some(20))],
+        ]],
+        windows
+    );
+}
+
+#[test]
+fn test_ramp_up_applies_to_joiners_per_phase() {
+    let windows = plan_windows(
+        &[grunt_spec(
+            "G",
+            1,
+            Some(RampUp::Stagger(ConfigDuration::Seconds(4))),
+        )],
+        None,
+        &[
+            Phase {
+                start: Duration::ZERO,
+                end: Some(Duration::from_secs(10)),
+                counts: vec![1],
+            },
+            Phase {
+                start: Duration::from_secs(10),
+                end: Some(Duration::from_secs(20)),
+                counts: vec![5],
+            },
+        ],
+    );
+
+    assert_eq!(
+        vec![vec![
+            vec![window(0, Some(20))],
+            vec![window(10, Some(20))],
+            vec![window(14, Some(20))],
+            vec![window(18, Some(20))],
+            // the fourth joiner would start at 22s, after the phase has already ended
+            vec![],
+        ]],
+        windows
+    );
+}
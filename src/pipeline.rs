@@ -1,13 +1,19 @@
-use url::Url;
-
 use crate::combinator::CombinatorHandler;
+use crate::events::{RunEvent, StepEvent};
 use crate::grunt::Grunt;
 use crate::http::HttpHandler;
 use crate::lua::LuaForPipeline;
 use crate::pipe_contents::PipeContents;
+use crate::seed::derive_seed;
+use crate::targets::Targets;
 use crate::validator::{Action as ValidatorAction, ValidatorHandler};
 
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
 use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant, SystemTime};
 
 pub mod action;
 use action::{ControlFlow, PipelineAction as PA, Reference};
@@ -19,15 +25,19 @@ use step_handler::{StepCompletion, StepError, StepHandler, StepHandlerInitError,
 #[allow(clippy::enum_variant_names)]
 pub enum PipelineStepResult {
     Ok,
-    OkWithWarnings(Vec<String>),
+    OkWithPause(Duration),
     OkWithExit,
 }
 
 impl From<StepCompletion> for PipelineStepResult {
     fn from(sc: StepCompletion) -> Self {
         match sc {
-            StepCompletion::Normal(..) | StepCompletion::NoIncrement(..) => Self::Ok,
-            StepCompletion::WithWarnings(_, warnings) => Self::OkWithWarnings(warnings),
+            // warnings make their way to the main thread by way of the step's event, to be
+            // tallied there
+            StepCompletion::Normal(..)
+            | StepCompletion::NoIncrement(..)
+            | StepCompletion::WithWarnings(..) => Self::Ok,
+            StepCompletion::WithPause(_, pause) => Self::OkWithPause(pause),
             StepCompletion::WithExit => Self::OkWithExit,
         }
     }
@@ -41,30 +51,48 @@ struct PipelineHandlers {
 }
 
 #[derive(Debug)]
-pub struct Pipeline<'lua, 'grunt, 'base_url> {
+pub struct Pipeline<'lua, 'grunt, 'targets> {
     pub data: Option<PipeContents>,
     pub grunt: &'grunt Grunt,
-    pub base_url: &'base_url Url,
+    pub targets: &'targets Targets,
 
     // this should ideally become private, but for now,
     // crate::pipeline::action::Reference::try_stringify_potential_lua_value has us pinned into a
     // bit of a corner that needs refactoring out of
     pub lua: Option<&'lua LuaForPipeline>,
 
+    // when set, HTTP requests are printed rather than sent (and answered with a placeholder
+    // response), validators are skipped, and GoTos end the pipeline rather than looping
+    pub dry_run: bool,
+
     idx: usize,
     goto_counters: HashMap<usize, usize>,
     handlers: PipelineHandlers,
+
+    // where RandomSleep durations come from, seeded from the grunt such that --seed reproduces
+    // them along with everything else
+    rng: StdRng,
+
+    // the instant the cycle time of the next Pace is measured from
+    pace_mark: Option<Instant>,
+
+    // where a StepEvent is sent after each step, if anywhere
+    events: Option<Sender<RunEvent>>,
 }
 
-impl<'lua, 'grunt, 'base_url> Pipeline<'lua, 'grunt, 'base_url> {
+impl<'lua, 'grunt, 'targets> Pipeline<'lua, 'grunt, 'targets> {
     pub fn new(
         grunt: &'grunt Grunt,
-        base_url: &'base_url Url,
+        targets: &'targets Targets,
         lua: Option<&'lua LuaForPipeline>,
+        dry_run: bool,
+        events: Option<Sender<RunEvent>>,
     ) -> Result<Self, StepHandlerInitError> {
         Ok(Self {
             grunt,
-            base_url,
+            targets,
+            dry_run,
+            events,
             data: None,
 
             handlers: PipelineHandlers {
@@ -75,6 +103,8 @@ impl<'lua, 'grunt, 'base_url> Pipeline<'lua, 'grunt, 'base_url> {
 
             lua,
             idx: 0,
+            rng: StdRng::seed_from_u64(derive_seed(grunt.seed, "sleep")),
+            pace_mark: None,
             goto_counters: HashMap::with_capacity(
                 grunt
                     .persona
@@ -97,37 +127,197 @@ impl<'lua, 'grunt, 'base_url> Pipeline<'lua, 'grunt, 'base_url> {
         self.handle_via(&self.handlers.validator, &PA::Validator(act.clone()))
     }
 
+    /// the index in the sequence of the step currently (or next to be) running
+    pub fn step_index(&self) -> usize {
+        self.idx
+    }
+
+    /// what the step currently (or next to be) running goes by
+    pub fn step_name(&self) -> &str {
+        self.grunt
+            .persona
+            .step_names
+            .get(self.idx)
+            .map_or("(end of sequence)", String::as_str)
+    }
+
+    /// puts the pipeline back at the top of the sequence with an empty pipe and fresh GoTo
+    /// counters, as if it had just been constructed
+    pub fn rewind(&mut self) {
+        self.idx = 0;
+        self.data = None;
+        self.goto_counters.clear();
+        self.pace_mark = None;
+    }
+
     fn step(&mut self, step: &PA) -> StepResult {
         match step {
+            PA::ControlFlow(ControlFlow::GoTo { index, .. }) if self.dry_run => {
+                println!(
+                    "[{}] step {}: would go to step {}, but dry runs walk the sequence only once",
+                    self.grunt.name, self.idx, index
+                );
+                Ok(StepCompletion::WithExit)
+            }
+
+            PA::Combinator(_) | PA::Validator(_) if self.dry_run => {
+                println!(
+                    "[{}] step {}: skipping {:?} in dry run",
+                    self.grunt.name, self.idx, step
+                );
+                Ok(StepCompletion::Normal(None))
+            }
+
+            PA::ControlFlow(
+                ControlFlow::Sleep(_) | ControlFlow::RandomSleep(_) | ControlFlow::Pace(_),
+            ) if self.dry_run => {
+                println!(
+                    "[{}] step {}: skipping {:?} in dry run",
+                    self.grunt.name, self.idx, step
+                );
+                Ok(StepCompletion::Normal(self.data.clone()))
+            }
+
+            PA::LuaFunction(function_name) if self.dry_run => {
+                println!(
+                    "[{}] step {}: running LuaFunction(\"{}\")",
+                    self.grunt.name, self.idx, function_name
+                );
+                self.step_lua_function(function_name)
+            }
+
             PA::ControlFlow(ControlFlow::GoTo { index, max_times }) => {
                 self.try_goto(*index, *max_times)
             }
 
-            PA::LuaFunction(function_name) => match self.lua {
-                None => Err(StepError::LuaNotInstantiated),
-                Some(lua) => {
-                    let user_ret = lua.run_user_script_function(function_name, self.data.as_ref());
-                    Ok(StepCompletion::Normal(
-                        user_ret.map(|rk| Some(PipeContents::LuaReference(rk)))?,
-                    ))
-                }
-            },
+            PA::ControlFlow(ControlFlow::Sleep(duration)) => Ok(StepCompletion::WithPause(
+                self.data.clone(),
+                duration.into(),
+            )),
+
+            PA::ControlFlow(ControlFlow::RandomSleep(distribution)) => {
+                let pause = distribution.sample(&mut self.rng);
+                Ok(StepCompletion::WithPause(self.data.clone(), pause))
+            }
+
+            PA::ControlFlow(ControlFlow::Pace(cycle)) => Ok(self.pace(cycle.into())),
+
+            PA::LuaFunction(function_name) => self.step_lua_function(function_name),
 
             PA::Reference(Reference::Value(..))
             | PA::Reference(Reference::LuaTableIndex(..))
             | PA::Reference(Reference::LuaTableValue(..))
             | PA::Reference(Reference::LuaValue) => Err(StepError::InvalidActionInContext),
 
+            PA::Include(_) | PA::Named(..) => Err(StepError::InvalidActionInContext),
+
             act @ PA::Http(_) => self.handle_via(&self.handlers.http, act),
             act @ PA::Combinator(_) => self.handle_via(&self.handlers.combinator, act),
             act @ PA::Validator(_) => self.handle_via(&self.handlers.validator, act),
         }
     }
 
+    fn step_lua_function(&self, function_name: &str) -> StepResult {
+        match self.lua {
+            None => Err(StepError::LuaNotInstantiated),
+            Some(lua) => {
+                let user_ret = lua.run_user_script_function(function_name, self.data.as_ref());
+                Ok(StepCompletion::Normal(
+                    user_ret.map(|rk| Some(PipeContents::LuaReference(rk)))?,
+                ))
+            }
+        }
+    }
+
     fn handle_via(&self, handler: &impl StepHandler, act: &PA) -> StepResult {
         handler.step(self, act)
     }
 
+    fn pace(&mut self, cycle: Duration) -> StepCompletion {
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(self.pace_mark.unwrap_or(now));
+
+        match cycle.checked_sub(elapsed) {
+            Some(pause) => {
+                self.pace_mark = Some(now + pause);
+                StepCompletion::WithPause(self.data.clone(), pause)
+            }
+
+            // running behind: start the next cycle now rather than trying to catch up. the message
+            // leaves out by how much, such that overruns tally up as a single warning
+            None => {
+                self.pace_mark = Some(now);
+                StepCompletion::WithWarnings(
+                    self.data.clone(),
+                    vec![format!(
+                        "iteration took longer than its pace of {}ms",
+                        cycle.as_millis()
+                    )],
+                )
+            }
+        }
+    }
+
+    fn send_event(&self, step: &PA, started: SystemTime, latency: Duration, result: &StepResult) {
+        let events = match &self.events {
+            Some(events) => events,
+            None => return,
+        };
+
+        let (method, url) = match step {
+            PA::Http(verb) => (
+                Some(verb.method()),
+                self.targets
+                    .resolve(verb.target())
+                    .and_then(|base| base.join(verb.url()).ok())
+                    .map(|url| url.to_string()),
+            ),
+            _ => (None, None),
+        };
+
+        let (response, warnings, error) = match result {
+            Ok(StepCompletion::Normal(data) | StepCompletion::NoIncrement(data)) => {
+                (data.as_ref(), vec![], None)
+            }
+            Ok(StepCompletion::WithWarnings(data, warnings)) => {
+                (data.as_ref(), warnings.clone(), None)
+            }
+            Ok(StepCompletion::WithPause(..) | StepCompletion::WithExit) => (None, vec![], None),
+            Err(err) => (None, vec![], Some(err)),
+        };
+
+        // only an Http step puts a response of its own in the pipe; anything else passing one
+        // through (a Sleep, say) didn't fetch it
+        let (status_code, bytes) = match (step, response) {
+            (
+                PA::Http(_),
+                Some(PipeContents::HttpResponse {
+                    status_code, body, ..
+                }),
+            ) => (Some(*status_code), Some(body.len())),
+            _ => (None, None),
+        };
+
+        // the receiving end only hangs up once the run is over, by which point nobody's listening
+        let _ = events.send(RunEvent::Step(StepEvent {
+            timestamp: started,
+            situation: self.grunt.situation.clone(),
+            persona: self.grunt.base_name.clone(),
+            grunt: self.grunt.name.clone(),
+            step: self.idx,
+            name: self.step_name().into(),
+            action: step.kind(),
+            method,
+            url,
+            status_code,
+            bytes,
+            latency,
+            warnings,
+            error: error.map(StepError::kind),
+            error_message: error.and_then(StepError::detail),
+        }));
+    }
+
     fn try_goto(&mut self, index: usize, max_times: Option<usize>) -> StepResult {
         if let Some(times) = max_times {
             if times == 0 {
@@ -167,15 +357,24 @@ impl Iterator for Pipeline<'_, '_, '_> {
     type Item = Result<PipelineStepResult, StepError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let ret = self
-            .grunt
-            .persona
-            .sequence
-            .get(self.idx)
-            .map(|step| self.step(step));
+        if self.pace_mark.is_none() {
+            self.pace_mark = Some(Instant::now());
+        }
+
+        let ret = self.grunt.persona.sequence.get(self.idx).map(|step| {
+            let started = SystemTime::now();
+            let timer = Instant::now();
+            let result = self.step(step);
+            self.send_event(step, started, timer.elapsed(), &result);
+            result
+        });
 
         self.data = match ret.as_ref() {
-            Some(Ok(StepCompletion::Normal(data) | StepCompletion::WithWarnings(data, _))) => {
+            Some(Ok(
+                StepCompletion::Normal(data)
+                | StepCompletion::WithWarnings(data, _)
+                | StepCompletion::WithPause(data, _),
+            )) => {
                 self.idx += 1;
                 data.clone()
             }
@@ -4,10 +4,12 @@ use rlua::{Error as LuaError, Value as LuaValue};
 use std::collections::HashMap;
 
 use crate::combinator::Action as CombinatorAction;
+use crate::config_duration::ConfigDuration;
 use crate::http::Action as HttpAction;
 use crate::lua::LuaForPipeline;
 use crate::pipe_contents::PipeContents as PC;
 use crate::pipeline::step_handler::StepError;
+use crate::sleep_distribution::SleepDistribution;
 use crate::validator::Action as ValidatorAction;
 
 pub type ConfigActionMap = HashMap<String, Reference>;
@@ -18,6 +20,18 @@ pub enum ControlFlow {
         index: usize,
         max_times: Option<usize>,
     },
+
+    // pauses the grunt for the given length of time before its next step. whatever is in the
+    // pipe is passed along untouched
+    Sleep(ConfigDuration),
+
+    // as Sleep, but for a length of time drawn at random (from the grunt's seeded generator)
+    RandomSleep(SleepDistribution),
+
+    // pauses for however much of the given cycle time is left since the previous Pace (or, for
+    // the first, since the pass began), such that each iteration of a loop takes the same
+    // wall-clock time no matter how quickly the service responds
+    Pace(ConfigDuration),
 }
 
 #[derive(Clone, Debug, DeRon)]
@@ -104,7 +118,64 @@ pub enum PipelineAction {
     Combinator(CombinatorAction),
     ControlFlow(ControlFlow),
     Http(HttpAction),
+
+    // splices in the named sequence fragment from the situation's includes. these are expanded
+    // away as situations are loaded, and never reach a pipeline
+    Include(String),
+
     LuaFunction(String),
+
+    // gives the wrapped LuaFunction or Combinator a name to go by in reports, events, and error
+    // messages (Http actions take a name of their own). unwrapped as grunts are built from their
+    // specs, and never reach a pipeline
+    Named(String, Box<PipelineAction>),
+
     Reference(Reference),
     Validator(ValidatorAction),
 }
+
+impl PipelineAction {
+    /// the name of the action's variant (or, for ControlFlow, the variant within), as seen in
+    /// situation files
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Combinator(_) => "Combinator",
+            Self::ControlFlow(ControlFlow::GoTo { .. }) => "GoTo",
+            Self::ControlFlow(ControlFlow::Sleep(_)) => "Sleep",
+            Self::ControlFlow(ControlFlow::RandomSleep(_)) => "RandomSleep",
+            Self::ControlFlow(ControlFlow::Pace(_)) => "Pace",
+            Self::Http(_) => "Http",
+            Self::Include(_) => "Include",
+            Self::LuaFunction(_) => "LuaFunction",
+            Self::Named(_, step) => step.kind(),
+            Self::Reference(_) => "Reference",
+            Self::Validator(_) => "Validator",
+        }
+    }
+
+    /// what the step, at the given index of its sequence, goes by in reports, events, and error
+    /// messages: the name it was given, if any, or else its method and URL (as written) for Http
+    /// actions, its function name for LuaFunctions, its kind and index for Combinators (whose
+    /// definitions make for unwieldy metric labels), and its definition for everything else
+    pub fn name(&self, index: usize) -> String {
+        match self {
+            Self::Named(name, _) => name.clone(),
+            Self::Http(verb) => verb
+                .name()
+                .map_or_else(|| format!("{} {}", verb.method(), verb.url()), String::from),
+            Self::LuaFunction(function) => function.clone(),
+            Self::Combinator(_) => format!("Combinator #{}", index),
+            Self::ControlFlow(control_flow) => format!("{:?}", control_flow),
+            Self::Validator(validator) => format!("{:?}", validator),
+            Self::Include(_) | Self::Reference(_) => format!("{:?}", self),
+        }
+    }
+
+    /// the step within, if this is a Named one, or else the step itself
+    pub fn unnamed(&self) -> &Self {
+        match self {
+            Self::Named(_, step) => step,
+            _ => self,
+        }
+    }
+}
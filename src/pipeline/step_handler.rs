@@ -1,6 +1,7 @@
 use rlua::Error as LuaError;
 
 use std::io::Error as IOError;
+use std::time::Duration;
 
 use crate::grunt::Grunt;
 use crate::pipe_contents::PipeContents;
@@ -21,6 +22,9 @@ pub enum StepCompletion {
     // TODO should this be a stronger type than just a string?
     WithWarnings(Option<PipeContents>, Vec<String>),
 
+    // the step succeeded, but the grunt should wait this long before taking the next one
+    WithPause(Option<PipeContents>, Duration),
+
     WithExit,
 }
 
@@ -45,6 +49,41 @@ pub enum StepError {
     ValidationSucceededUnexpectedly,
 }
 
+impl StepError {
+    /// a short, stable name for the kind of error, for use in machine-readable output
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Http(_) => "http",
+            Self::IO(_) => "io",
+            Self::InvalidActionInContext => "invalid_action_in_context",
+            Self::LuaNotInstantiated => "lua_not_instantiated",
+            Self::LuaException(_) => "lua_exception",
+            Self::RefuseToStringifyComplexLuaValue => "complex_lua_value",
+            Self::RefuseToStringifyNonExistantValue => "nonexistent_lua_value",
+            Self::RequestedLuaValueWhereNoneExists => "no_lua_value",
+            Self::Unclassified => "unclassified",
+            Self::UrlParsing(_) => "url_parsing",
+            Self::Validation(_) => "validation",
+            Self::ValidationSucceededUnexpectedly => "validation_succeeded_unexpectedly",
+        }
+    }
+
+    /// what went wrong, in words, for those errors which say more than their kind does
+    pub fn detail(&self) -> Option<String> {
+        match self {
+            Self::Http(err) => Some(err.to_string()),
+            Self::IO(err) => Some(err.to_string()),
+            Self::LuaException(err) => Some(err.to_string()),
+            Self::UrlParsing(err) => Some(err.to_string()),
+            Self::Validation(message) => Some(message.clone()),
+            Self::ValidationSucceededUnexpectedly => {
+                Some("a validation succeeded where a failure was expected".into())
+            }
+            _ => None,
+        }
+    }
+}
+
 impl From<IOError> for StepError {
     fn from(err: IOError) -> Self {
         Self::IO(err)
@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::events::RunEvent;
+use crate::summary::RequestStats;
+
+#[cfg(test)]
+use crate::events::{GruntEvent, StepEvent};
+
+// the percentiles shown in each progress line
+const PERCENTILES: [f64; 3] = [50.0, 95.0, 99.0];
+
+#[derive(Debug, Default)]
+struct SituationProgress {
+    active_grunts: usize,
+
+    // requests finished since the last report
+    window: RequestStats,
+}
+
+/// the state of the run as of the last handful of seconds, built up from the RunEvents sent back
+/// by grunts, for the progress lines printed every --progress interval
+#[derive(Debug)]
+pub struct Progress {
+    started: Instant,
+    last_report: Instant,
+    situations: BTreeMap<String, SituationProgress>,
+}
+
+impl Progress {
+    pub fn new<'a>(situations: impl IntoIterator<Item = &'a str>, started: Instant) -> Self {
+        Self {
+            started,
+            last_report: started,
+            situations: situations
+                .into_iter()
+                .map(|name| (name.into(), SituationProgress::default()))
+                .collect(),
+        }
+    }
+
+    pub fn record(&mut self, event: &RunEvent) {
+        match event {
+            RunEvent::Step(step) => {
+                if let Some(progress) = self.situations.get_mut(&step.situation) {
+                    progress.window.record(step);
+                }
+            }
+            RunEvent::GruntStarted(grunt) => {
+                if let Some(progress) = self.situations.get_mut(&grunt.situation) {
+                    progress.active_grunts += 1;
+                }
+            }
+            RunEvent::GruntStopped(grunt) => {
+                if let Some(progress) = self.situations.get_mut(&grunt.situation) {
+                    progress.active_grunts = progress.active_grunts.saturating_sub(1);
+                }
+            }
+        }
+    }
+
+    /// a line for each situation covering the time since the previous report (or the start of
+    /// the run), after which that time is forgotten
+    pub fn report(&mut self, now: Instant) -> String {
+        let elapsed = now.saturating_duration_since(self.started);
+        let window = now.saturating_duration_since(self.last_report);
+        self.last_report = now;
+
+        let mut out = String::new();
+        for (name, progress) in &mut self.situations {
+            let stats = std::mem::take(&mut progress.window);
+
+            let _ = write!(
+                out,
+                "[{}] {} in: {} grunt{} active, {:.1} req/s, {:.1}% errors",
+                name,
+                format_elapsed(elapsed),
+                progress.active_grunts,
+                if progress.active_grunts == 1 { "" } else { "s" },
+                rate(stats.count, window),
+                stats.error_rate() * 100.0,
+            );
+
+            if stats.latencies.count() > 0 {
+                for percent in PERCENTILES {
+                    let _ = write!(
+                        out,
+                        ", p{} {:.1}ms",
+                        percent,
+                        stats.latencies.percentile(percent).as_secs_f64() * 1000.0
+                    );
+                }
+            }
+
+            let _ = writeln!(out);
+        }
+
+        out
+    }
+}
+
+fn rate(count: u64, over: Duration) -> f64 {
+    if over.is_zero() {
+        return 0.0;
+    }
+
+    count as f64 / over.as_secs_f64()
+}
+
+// whole seconds, in the same units --duration takes
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, secs) => format!("{}s", secs),
+        (0, mins, secs) => format!("{}m{:02}s", mins, secs),
+        (hours, mins, secs) => format!("{}h{:02}m{:02}s", hours, mins, secs),
+    }
+}
+
+#[cfg(test)]
+fn grunt_event(situation: &str) -> GruntEvent {
+    GruntEvent {
+        situation: situation.into(),
+        persona: "Buyer".into(),
+    }
+}
+
+#[test]
+fn test_report_covers_window_since_last() {
+    let started = Instant::now();
+    let mut progress = Progress::new(["checkout", "browse"], started);
+
+    progress.record(&RunEvent::GruntStarted(grunt_event("checkout")));
+    progress.record(&RunEvent::GruntStarted(grunt_event("checkout")));
+    progress.record(&RunEvent::GruntStopped(grunt_event("checkout")));
+    progress.record(&RunEvent::GruntStarted(grunt_event("browse")));
+    for _ in 0..19 {
+        progress.record(&RunEvent::Step(StepEvent::test_http(
+            "GET /cart",
+            Some(200),
+            Duration::from_millis(100),
+        )));
+    }
+    progress.record(&RunEvent::Step(StepEvent::test_http(
+        "GET /cart",
+        None,
+        Duration::ZERO,
+    )));
+
+    assert_eq!(
+        "[browse] 10s in: 1 grunt active, 0.0 req/s, 0.0% errors\n\
+         [checkout] 10s in: 1 grunt active, 2.0 req/s, 5.0% errors, p50 100.0ms, p95 100.0ms, p99 100.0ms\n",
+        progress.report(started + Duration::from_secs(10))
+    );
+
+    progress.record(&RunEvent::GruntStopped(grunt_event("checkout")));
+    progress.record(&RunEvent::Step(StepEvent::test_http(
+        "GET /cart",
+        Some(500),
+        Duration::from_millis(100),
+    )));
+
+    assert_eq!(
+        "[browse] 1m15s in: 1 grunt active, 0.0 req/s, 0.0% errors\n\
+         [checkout] 1m15s in: 0 grunts active, 0.0 req/s, 100.0% errors, p50 100.0ms, p95 100.0ms, p99 100.0ms\n",
+        progress.report(started + Duration::from_secs(75))
+    );
+}
+
+#[test]
+fn test_format_elapsed() {
+    assert_eq!("0s", format_elapsed(Duration::from_millis(999)));
+    assert_eq!("59s", format_elapsed(Duration::from_secs(59)));
+    assert_eq!("20m00s", format_elapsed(Duration::from_secs(1200)));
+    assert_eq!("1h01m05s", format_elapsed(Duration::from_secs(3665)));
+}
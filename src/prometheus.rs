@@ -0,0 +1,401 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write as FmtWrite};
+use std::io::{BufRead, BufReader, Result as IOResult, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::events::{RunEvent, StepEvent};
+
+#[cfg(test)]
+use crate::events::GruntEvent;
+#[cfg(test)]
+use std::io::Read;
+#[cfg(test)]
+use std::thread;
+
+// the upper bounds (in seconds) of the request latency histogram's buckets: Prometheus' own
+// defaults, which suit most web services well enough
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+// how long a scraper gets to send its request before it's hung up on, such that one stuck
+// connection can't hold up the rest for long
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug, Default)]
+struct LatencyBuckets {
+    // cumulative, as Prometheus expects: each bucket counts every value at or under its bound
+    counts: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl LatencyBuckets {
+    fn record(&mut self, latency: Duration) {
+        let secs = latency.as_secs_f64();
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(&mut self.counts) {
+            if secs <= *bound {
+                *count += 1;
+            }
+        }
+
+        self.count += 1;
+        self.sum += secs;
+    }
+}
+
+// situation, persona, and step name
+type StepKey = (String, String, String);
+
+/// the metrics served at --metrics-listen, built up from the RunEvents sent back by grunts
+#[derive(Debug, Default)]
+pub struct Metrics {
+    // keyed by step and status code (or "error", for requests which got no response)
+    requests: BTreeMap<(StepKey, String), u64>,
+
+    // of requests which got a response, of any status
+    latencies: BTreeMap<StepKey, LatencyBuckets>,
+
+    // keyed by step and the kind of StepError
+    validation_failures: BTreeMap<(StepKey, &'static str), u64>,
+
+    lua_errors: BTreeMap<StepKey, u64>,
+
+    // keyed by situation and persona
+    active_grunts: BTreeMap<(String, String), usize>,
+}
+
+impl Metrics {
+    pub fn record(&mut self, event: &RunEvent) {
+        match event {
+            RunEvent::Step(step) => self.record_step(step),
+            RunEvent::GruntStarted(grunt) => {
+                *self
+                    .active_grunts
+                    .entry((grunt.situation.clone(), grunt.persona.clone()))
+                    .or_default() += 1;
+            }
+            RunEvent::GruntStopped(grunt) => {
+                let active = self
+                    .active_grunts
+                    .entry((grunt.situation.clone(), grunt.persona.clone()))
+                    .or_default();
+                *active = active.saturating_sub(1);
+            }
+        }
+    }
+
+    fn record_step(&mut self, event: &StepEvent) {
+        let key = || {
+            (
+                event.situation.clone(),
+                event.persona.clone(),
+                event.name.clone(),
+            )
+        };
+
+        if event.method.is_some() {
+            let status = event
+                .status_code
+                .map_or_else(|| "error".into(), |status_code| status_code.to_string());
+            *self.requests.entry((key(), status)).or_default() += 1;
+
+            if event.status_code.is_some() {
+                self.latencies
+                    .entry(key())
+                    .or_default()
+                    .record(event.latency);
+            }
+        }
+
+        // see StepError::kind
+        match event.error {
+            Some(kind @ ("validation" | "validation_succeeded_unexpectedly")) => {
+                *self.validation_failures.entry((key(), kind)).or_default() += 1;
+            }
+            Some("lua_exception" | "lua_not_instantiated") => {
+                *self.lua_errors.entry(key()).or_default() += 1;
+            }
+            _ => {}
+        }
+    }
+
+    /// the metrics in Prometheus' text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        write_family(
+            &mut out,
+            "seatrial_requests_total",
+            "counter",
+            "HTTP requests sent, by response status (\"error\" for those which got no response)",
+            self.requests
+                .iter()
+                .map(|((step, status), count)| (step_labels(step, &[("status", status)]), count)),
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP seatrial_request_duration_seconds latency of HTTP requests which got a response"
+        );
+        let _ = writeln!(out, "# TYPE seatrial_request_duration_seconds histogram");
+        for (step, buckets) in &self.latencies {
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(&buckets.counts) {
+                let _ = writeln!(
+                    out,
+                    "seatrial_request_duration_seconds_bucket{} {}",
+                    step_labels(step, &[("le", &bound.to_string())]),
+                    count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "seatrial_request_duration_seconds_bucket{} {}",
+                step_labels(step, &[("le", "+Inf")]),
+                buckets.count
+            );
+            let _ = writeln!(
+                out,
+                "seatrial_request_duration_seconds_sum{} {}",
+                step_labels(step, &[]),
+                buckets.sum
+            );
+            let _ = writeln!(
+                out,
+                "seatrial_request_duration_seconds_count{} {}",
+                step_labels(step, &[]),
+                buckets.count
+            );
+        }
+
+        write_family(
+            &mut out,
+            "seatrial_validation_failures_total",
+            "counter",
+            "validator and combinator steps which failed, by kind of failure",
+            self.validation_failures
+                .iter()
+                .map(|((step, kind), count)| (step_labels(step, &[("kind", kind)]), count)),
+        );
+
+        write_family(
+            &mut out,
+            "seatrial_lua_errors_total",
+            "counter",
+            "steps which failed on an error raised by (or looking for) a Lua function",
+            self.lua_errors
+                .iter()
+                .map(|(step, count)| (step_labels(step, &[]), count)),
+        );
+
+        write_family(
+            &mut out,
+            "seatrial_active_grunts",
+            "gauge",
+            "grunts past their ramp-up (or the start of their phase) and not yet done",
+            self.active_grunts
+                .iter()
+                .map(|((situation, persona), count)| {
+                    (
+                        labels(&[("situation", situation), ("persona", persona)]),
+                        count,
+                    )
+                }),
+        );
+
+        out
+    }
+}
+
+/// answers requests for /metrics on the given listener with the current state of the metrics,
+/// one at a time, until the process exits
+pub fn serve(listener: TcpListener, metrics: Arc<Mutex<Metrics>>) {
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| respond(&stream, &metrics));
+        if let Err(err) = result {
+            eprintln!("[seatrial] error serving metrics: {}", err);
+        }
+    }
+}
+
+fn respond(stream: &TcpStream, metrics: &Mutex<Metrics>) -> IOResult<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // headers are of no interest, but are read anyway such that the client isn't hung up on
+    // mid-request
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) if path == "/metrics" || path.starts_with("/metrics?") => (
+            "200 OK",
+            metrics
+                .lock()
+                .expect("internal error: metrics lock poisoned")
+                .render(),
+        ),
+        _ => ("404 Not Found", "metrics are served at /metrics\n".into()),
+    };
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn write_family<L: Display, V: Display>(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    samples: impl Iterator<Item = (L, V)>,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{}{} {}", name, labels, value);
+    }
+}
+
+fn step_labels((situation, persona, step): &StepKey, extra: &[(&str, &str)]) -> String {
+    let mut pairs = vec![
+        ("situation", situation.as_str()),
+        ("persona", persona.as_str()),
+        ("step", step.as_str()),
+    ];
+    pairs.extend_from_slice(extra);
+
+    labels(&pairs)
+}
+
+fn labels(pairs: &[(&str, &str)]) -> String {
+    let pairs: Vec<String> = pairs
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", name, value)
+        })
+        .collect();
+
+    format!("{{{}}}", pairs.join(","))
+}
+
+#[test]
+fn test_render() {
+    let mut metrics = Metrics::default();
+    let grunt = GruntEvent {
+        situation: "checkout".into(),
+        persona: "Buyer".into(),
+    };
+
+    metrics.record(&RunEvent::GruntStarted(grunt.clone()));
+    metrics.record(&RunEvent::GruntStarted(grunt.clone()));
+    metrics.record(&RunEvent::GruntStopped(grunt));
+    for status_code in [Some(200), Some(200), None] {
+        metrics.record(&RunEvent::Step(StepEvent::test_http(
+            "view \"cart\"",
+            status_code,
+            Duration::from_millis(30),
+        )));
+    }
+    metrics.record(&RunEvent::Step(StepEvent {
+        error: Some("validation"),
+        ..StepEvent::test_validator("AssertStatusCode(200)")
+    }));
+    metrics.record(&RunEvent::Step(StepEvent {
+        action: "LuaFunction",
+        error: Some("lua_exception"),
+        ..StepEvent::test_validator("pick")
+    }));
+
+    let rendered = metrics.render();
+    let cart = r#"situation="checkout",persona="Buyer",step="view \"cart\"""#;
+
+    for expected in [
+        format!(r#"seatrial_requests_total{{{},status="200"}} 2"#, cart),
+        format!(r#"seatrial_requests_total{{{},status="error"}} 1"#, cart),
+        format!(
+            r#"seatrial_request_duration_seconds_bucket{{{},le="0.025"}} 0"#,
+            cart
+        ),
+        format!(
+            r#"seatrial_request_duration_seconds_bucket{{{},le="0.05"}} 2"#,
+            cart
+        ),
+        format!(
+            r#"seatrial_request_duration_seconds_bucket{{{},le="+Inf"}} 2"#,
+            cart
+        ),
+        format!(r#"seatrial_request_duration_seconds_count{{{}}} 2"#, cart),
+        r#"seatrial_validation_failures_total{situation="checkout",persona="Buyer",step="AssertStatusCode(200)",kind="validation"} 1"#.into(),
+        r#"seatrial_lua_errors_total{situation="checkout",persona="Buyer",step="pick"} 1"#.into(),
+        r#"seatrial_active_grunts{situation="checkout",persona="Buyer"} 1"#.into(),
+        "# TYPE seatrial_request_duration_seconds histogram".into(),
+    ] {
+        assert!(
+            rendered.lines().any(|line| line == expected),
+            "{} not in:\n{}",
+            expected,
+            rendered
+        );
+    }
+}
+
+#[test]
+fn test_serve() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let metrics = Arc::new(Mutex::new(Metrics::default()));
+    metrics
+        .lock()
+        .unwrap()
+        .record(&RunEvent::Step(StepEvent::test_http(
+            "view cart",
+            Some(200),
+            Duration::from_millis(30),
+        )));
+
+    thread::spawn(move || serve(listener, metrics));
+
+    let get = |path: &str| {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+
+    let response = get("/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(
+        response.contains(r#"step="view cart",status="200"} 1"#),
+        "{}",
+        response
+    );
+
+    let response = get("/");
+    assert!(
+        response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{}",
+        response
+    );
+}
@@ -0,0 +1,56 @@
+use nanoserde::DeRon;
+
+use std::time::Duration;
+
+use crate::config_duration::ConfigDuration;
+
+#[derive(Clone, Debug, DeRon)]
+pub enum RampUp {
+    // spread grunt start times evenly across the given duration, such that the first grunt
+    // starts immediately and the last starts just shy of the duration's end
+    Linear(ConfigDuration),
+
+    // start each grunt this long after the one before it
+    Stagger(ConfigDuration),
+}
+
+impl RampUp {
+    /// how long the grunt in `slot` (zero-indexed, of `total` grunts sharing this ramp-up) should
+    /// wait after the run starts before taking its first step
+    pub fn start_delay(&self, slot: usize, total: usize) -> Duration {
+        match self {
+            Self::Linear(over) => {
+                if total == 0 {
+                    return Duration::ZERO;
+                }
+
+                Duration::from(over).mul_f64(slot as f64 / total as f64)
+            }
+            Self::Stagger(each) => Duration::from(each) * slot as u32,
+        }
+    }
+}
+
+#[test]
+fn test_linear() {
+    let ramp = RampUp::Linear(ConfigDuration::Seconds(10));
+
+    assert_eq!(Duration::ZERO, ramp.start_delay(0, 5));
+    assert_eq!(Duration::from_secs(2), ramp.start_delay(1, 5));
+    assert_eq!(Duration::from_secs(8), ramp.start_delay(4, 5));
+}
+
+#[test]
+fn test_linear_single_grunt() {
+    let ramp = RampUp::Linear(ConfigDuration::Seconds(10));
+
+    assert_eq!(Duration::ZERO, ramp.start_delay(0, 1));
+}
+
+#[test]
+fn test_stagger() {
+    let ramp = RampUp::Stagger(ConfigDuration::Milliseconds(250));
+
+    assert_eq!(Duration::ZERO, ramp.start_delay(0, 3));
+    assert_eq!(Duration::from_millis(500), ramp.start_delay(2, 3));
+}
@@ -0,0 +1,218 @@
+use nanoserde::{DeJson, SerJson};
+
+use std::fs::read_to_string;
+use std::io::{Error as IOError, ErrorKind as IOErrorKind, Result as IOResult};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::summary::{RequestStats, RunSummary};
+
+#[cfg(test)]
+use crate::events::StepEvent;
+
+/// the latencies of a step's requests which got a response, in (fractional) milliseconds
+#[derive(Clone, Debug, DeJson, PartialEq, SerJson)]
+pub struct LatencyReport {
+    pub min: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+/// how the HTTP requests of a single step of a single persona fared over a run
+#[derive(Clone, Debug, DeJson, PartialEq, SerJson)]
+pub struct StepReport {
+    pub situation: String,
+    pub persona: String,
+    pub step: String,
+    pub count: u64,
+    pub errors: u64,
+
+    // requests per second across the whole run
+    pub throughput: f64,
+
+    // left out when none of the step's requests got a response
+    pub latency_ms: Option<LatencyReport>,
+}
+
+impl StepReport {
+    /// the share (0 to 1) of requests counted as errors
+    pub fn error_rate(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        self.errors as f64 / self.count as f64
+    }
+}
+
+/// the machine-readable counterpart to the summary table, written with --report and read back
+/// by seatrial compare
+#[derive(Clone, Debug, DeJson, PartialEq, SerJson)]
+pub struct RunReport {
+    pub seatrial_version: String,
+    pub seed: u64,
+
+    // when the first step started, in milliseconds since the Unix epoch, and how long it was until
+    // the last one finished
+    pub started_ms: u64,
+    pub elapsed_ms: f64,
+
+    pub steps: Vec<StepReport>,
+
+    // open-model situations which dropped any arrivals; left out when none did
+    pub dropped_arrivals: Option<Vec<DroppedArrivalsReport>>,
+}
+
+/// how many arrivals an open-model situation dropped, as every one of its grunts was busy
+#[derive(Clone, Debug, DeJson, PartialEq, SerJson)]
+pub struct DroppedArrivalsReport {
+    pub situation: String,
+    pub count: u64,
+}
+
+impl RunReport {
+    pub fn new(summary: &RunSummary, seed: u64) -> Self {
+        let elapsed = summary.elapsed();
+        let dropped_arrivals: Vec<DroppedArrivalsReport> = summary
+            .all_dropped_arrivals()
+            .map(|(situation, count)| DroppedArrivalsReport {
+                situation: situation.clone(),
+                count: *count,
+            })
+            .collect();
+
+        Self {
+            seatrial_version: env!("CARGO_PKG_VERSION").into(),
+            seed,
+            started_ms: summary
+                .started()
+                .and_then(|started| started.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |since| since.as_millis() as u64),
+            elapsed_ms: millis(elapsed),
+            steps: summary
+                .requests()
+                .map(|((situation, persona, step), stats)| StepReport {
+                    situation: situation.clone(),
+                    persona: persona.clone(),
+                    step: step.clone(),
+                    count: stats.count,
+                    errors: stats.errors,
+                    throughput: if elapsed.is_zero() {
+                        0.0
+                    } else {
+                        stats.count as f64 / elapsed.as_secs_f64()
+                    },
+                    latency_ms: latency_report(stats),
+                })
+                .collect(),
+            dropped_arrivals: if dropped_arrivals.is_empty() {
+                None
+            } else {
+                Some(dropped_arrivals)
+            },
+        }
+    }
+
+    pub fn load(path: &Path) -> IOResult<Self> {
+        Self::deserialize_json(&read_to_string(path)?)
+            .map_err(|err| IOError::new(IOErrorKind::InvalidData, err.to_string()))
+    }
+}
+
+/// the latencies of the given requests, or None if none of them got a response
+pub fn latency_report(stats: &RequestStats) -> Option<LatencyReport> {
+    let hist = &stats.latencies;
+    if hist.count() == 0 {
+        return None;
+    }
+
+    Some(LatencyReport {
+        min: millis(hist.min()),
+        mean: millis(hist.mean()),
+        p50: millis(hist.percentile(50.0)),
+        p90: millis(hist.percentile(90.0)),
+        p95: millis(hist.percentile(95.0)),
+        p99: millis(hist.percentile(99.0)),
+        max: millis(hist.max()),
+    })
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[test]
+fn test_round_trip() {
+    let mut summary = RunSummary::default();
+    for (status_code, latency_ms) in [(Some(200), 10), (Some(500), 30), (None, 0)] {
+        summary.record(&StepEvent {
+            timestamp: UNIX_EPOCH + Duration::from_secs(1_650_000_000),
+            ..StepEvent::test_http("view cart", status_code, Duration::from_millis(latency_ms))
+        });
+    }
+
+    summary.record_dropped_arrivals("checkout", 7);
+
+    let report = RunReport::new(&summary, 42);
+    assert_eq!(1_650_000_000_000, report.started_ms);
+    assert_eq!(30.0, report.elapsed_ms);
+    assert_eq!(1, report.steps.len());
+
+    let step = &report.steps[0];
+    assert_eq!(
+        ("checkout", "Buyer", "view cart"),
+        (
+            step.situation.as_str(),
+            step.persona.as_str(),
+            step.step.as_str()
+        )
+    );
+    assert_eq!((3, 2), (step.count, step.errors));
+    assert_eq!(100.0, step.throughput);
+    assert_eq!(
+        Some(10.0),
+        step.latency_ms.as_ref().map(|latency| latency.min)
+    );
+
+    assert_eq!(
+        Some(vec![DroppedArrivalsReport {
+            situation: "checkout".into(),
+            count: 7
+        }]),
+        report.dropped_arrivals
+    );
+
+    assert_eq!(
+        report,
+        RunReport::deserialize_json(&report.serialize_json()).unwrap()
+    );
+}
+
+#[test]
+fn test_latency_left_out_without_responses() {
+    let report = RunReport {
+        seatrial_version: "0.0.0".into(),
+        seed: 1,
+        started_ms: 0,
+        elapsed_ms: 0.0,
+        steps: vec![StepReport {
+            situation: "checkout".into(),
+            persona: "Buyer".into(),
+            step: "view cart".into(),
+            count: 1,
+            errors: 1,
+            throughput: 0.0,
+            latency_ms: None,
+        }],
+        dropped_arrivals: None,
+    };
+
+    let json = report.serialize_json();
+    assert!(!json.contains("latency_ms"), "{}", json);
+    assert!(!json.contains("dropped_arrivals"), "{}", json);
+    assert_eq!(report, RunReport::deserialize_json(&json).unwrap());
+}
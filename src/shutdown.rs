@@ -0,0 +1,121 @@
+use signal_hook::consts::TERM_SIGNALS;
+use signal_hook::flag::{register, register_conditional_shutdown};
+
+use std::io::Result as IOResult;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// the exit status used when a second signal forces an immediate exit, per the shell convention
+// of 128 + SIGINT
+const FORCED_EXIT_STATUS: i32 = 130;
+
+// the longest we'll sleep in one go before checking whether we've been asked to stop
+const SLEEP_SLICE: Duration = Duration::from_millis(100);
+
+/// arranges for the first SIGINT/SIGTERM/SIGQUIT to flip the returned flag (asking everyone to
+/// wrap up gracefully), and for any further signal to exit the process immediately
+pub fn install_signal_handlers() -> IOResult<Arc<AtomicBool>> {
+    let interrupted = Arc::new(AtomicBool::new(false));
+
+    for signal in TERM_SIGNALS {
+        // order matters here: the conditional shutdown must be registered first, such that it
+        // sees the flag as it was *before* this signal set it
+        register_conditional_shutdown(*signal, FORCED_EXIT_STATUS, interrupted.clone())?;
+        register(*signal, interrupted.clone())?;
+    }
+
+    Ok(interrupted)
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StopReason {
+    Interrupted,
+    OutOfTime,
+}
+
+/// the reasons a grunt (or arrival scheduler) might need to wind down before it runs out of
+/// things to do on its own: the user interrupting the run, or the situation's duration elapsing
+#[derive(Clone, Debug)]
+pub struct StopCondition {
+    deadline: Option<Instant>,
+    interrupted: Arc<AtomicBool>,
+}
+
+impl StopCondition {
+    pub fn new(deadline: Option<Instant>, interrupted: Arc<AtomicBool>) -> Self {
+        Self {
+            deadline,
+            interrupted,
+        }
+    }
+
+    pub fn has_deadline(&self) -> bool {
+        self.deadline.is_some()
+    }
+
+    pub fn check(&self) -> Option<StopReason> {
+        if self.interrupted.load(Ordering::Relaxed) {
+            Some(StopReason::Interrupted)
+        } else if self
+            .deadline
+            .map_or(false, |deadline| Instant::now() >= deadline)
+        {
+            Some(StopReason::OutOfTime)
+        } else {
+            None
+        }
+    }
+
+    /// sleeps for up to `duration`, waking early (and returning the reason) if asked to stop
+    pub fn sleep(&self, duration: Duration) -> Option<StopReason> {
+        let until = Instant::now() + duration;
+
+        loop {
+            if let Some(reason) = self.check() {
+                return Some(reason);
+            }
+
+            let now = Instant::now();
+            if now >= until {
+                return None;
+            }
+
+            thread::sleep((until - now).min(SLEEP_SLICE));
+        }
+    }
+}
+
+#[test]
+fn test_out_of_time() {
+    let stop = StopCondition::new(Some(Instant::now()), Arc::new(AtomicBool::new(false)));
+
+    assert_eq!(Some(StopReason::OutOfTime), stop.check());
+}
+
+#[test]
+fn test_interrupted_wins() {
+    let stop = StopCondition::new(Some(Instant::now()), Arc::new(AtomicBool::new(true)));
+
+    assert_eq!(Some(StopReason::Interrupted), stop.check());
+}
+
+#[test]
+fn test_sleep_wakes_on_interrupt() {
+    let stop = StopCondition::new(None, Arc::new(AtomicBool::new(true)));
+    let started = Instant::now();
+
+    assert_eq!(
+        Some(StopReason::Interrupted),
+        stop.sleep(Duration::from_secs(60))
+    );
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn test_sleep_runs_to_completion() {
+    let stop = StopCondition::new(None, Arc::new(AtomicBool::new(false)));
+
+    assert_eq!(None, stop.sleep(Duration::from_millis(10)));
+}
@@ -1,43 +1,120 @@
 use nanoserde::{DeRon, DeRonErr};
 use url::Url;
 
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
 use std::fs::{canonicalize, read_to_string};
-use std::path::PathBuf;
-use std::str::FromStr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use crate::arrival_rate::ArrivalRate;
+use crate::config_duration::ConfigDuration;
 use crate::grunt::{Grunt, GruntSpec};
+use crate::interpolation::{interpolate, Variables};
+use crate::library::Library;
+use crate::persona::{Persona, PersonaSpec};
+use crate::phase::{plan_windows, Phase, PhaseSpec};
+use crate::pipeline::action::PipelineAction;
+use crate::ramp_up::RampUp;
+use crate::seed::derive_seed;
+use crate::targets::{parse_base_url, TargetOverride, Targets};
+use crate::threshold::Threshold;
 
 // built out of a SituationSpec after post-parse contextual validations have been run
 #[derive(Clone, Debug)]
 pub struct Situation {
-    pub base_url: Url,
+    pub name: String,
+    pub targets: Targets,
     pub lua_file: Option<PathBuf>,
     pub grunts: Vec<Grunt>,
+
+    // when set, grunts loop their personas until this much wall-clock time has passed, rather
+    // than retiring upon reaching the end of their sequence
+    pub duration: Option<Duration>,
+
+    // when set, this is an open-model situation: rather than each grunt walking its persona as
+    // fast as responses allow, a new persona iteration is handed to an idle grunt this often
+    pub arrival_interval: Option<Duration>,
+
+    // checked against the situation's requests once the run is over
+    pub thresholds: Vec<Threshold>,
 }
 
 impl Situation {
     pub fn from_spec(
         spec: &SituationSpec,
-        base_url: &Url,
+        default_base_url: &Url,
+        target_overrides: &[TargetOverride],
         grunt_multiplier: usize,
+        default_duration: Option<&ConfigDuration>,
+        run_seed: u64,
     ) -> Result<Self, SituationParseErr> {
+        let seed = derive_seed(run_seed, &spec.name());
+
+        let phases = spec.phases(grunt_multiplier, default_duration)?;
+
+        // a situation with phases lasts exactly as long as they do, and no longer
+        let duration: Option<Duration> = match phases.last() {
+            Some(Phase { end: Some(end), .. }) => Some(*end),
+            _ => spec
+                .contents
+                .duration
+                .as_ref()
+                .or(default_duration)
+                .map(|duration| duration.into()),
+        };
+
         let grunts = {
-            let mut grunts: Vec<Grunt> = Vec::new();
+            let windows = plan_windows(
+                &spec.contents.grunts,
+                spec.contents.ramp_up.as_ref(),
+                &phases,
+            );
 
-            for grunt_spec in spec.contents.grunts.iter() {
-                grunts.extend(Grunt::from_spec_with_multiplier(
-                    grunt_spec,
-                    grunt_multiplier,
-                )?);
+            let name = spec.name();
+            let mut grunts: Vec<Grunt> = Vec::new();
+            for (grunt_spec, copies) in spec.contents.grunts.iter().zip(windows) {
+                for (copy, windows) in copies.into_iter().enumerate() {
+                    grunts.push(Grunt::from_spec(grunt_spec, copy, windows, &name, seed));
+                }
             }
 
             grunts
         };
 
+        let arrival_interval = spec.arrival_interval()?;
+        if arrival_interval.is_some() && duration.is_none() {
+            return Err(spec.semantics_err(
+                "arrival_rate requires a duration, either in the situation or on the command line"
+                    .into(),
+            ));
+        }
+
+        let targets = spec.targets(default_base_url, target_overrides)?;
+
+        for grunt_spec in &spec.contents.grunts {
+            for step in &grunt_spec.persona().sequence {
+                if let PipelineAction::Http(verb) = step {
+                    if targets.resolve(verb.target()).is_none() {
+                        return Err(spec.semantics_err(format!(
+                            "request to \"{}\" names target \"{}\", which the situation doesn't define",
+                            verb.url(),
+                            verb.target().unwrap_or_default(),
+                        )));
+                    }
+                }
+            }
+        }
+
+        let thresholds = spec.thresholds()?;
+
         Ok(Self {
             grunts,
-            base_url: base_url.clone(),
+            duration,
+            arrival_interval,
+            thresholds,
+            targets,
+            name: spec.name(),
             lua_file: spec.canonical_lua_file(),
         })
     }
@@ -50,6 +127,314 @@ pub struct SituationSpec {
 }
 
 impl SituationSpec {
+    /// reads and parses the situation config at the given path, first replacing any ${VAR}
+    /// placeholders in it with their values
+    pub fn load(path: &str, vars: &Variables) -> Result<Self, SituationParseErr> {
+        let source = canonicalize(path)?.into_os_string().into_string()?;
+        let text =
+            interpolate(&read_to_string(&source)?, vars).map_err(|message| SituationParseErr {
+                kind: SituationParseErrKind::Semantics {
+                    message,
+                    location: source.clone(),
+                },
+            })?;
+
+        let mut spec = Self {
+            contents: DeRon::deserialize_ron(&text)?,
+            source,
+        };
+        spec.resolve_personas(vars)?;
+
+        Ok(spec)
+    }
+
+    // loads any included files, fills in the persona of each grunt naming one from them, and
+    // splices included fragments into every sequence, such that nothing downstream needs to know
+    // includes exist
+    fn resolve_personas(&mut self, vars: &Variables) -> Result<(), SituationParseErr> {
+        let mut library = Library::default();
+
+        for file in self.contents.includes.iter().flatten() {
+            let path = self.relative_to_source(file);
+            library.include(&path, vars).map_err(|err| {
+                self.semantics_err(format!("in included file {}: {}", path.display(), err))
+            })?;
+        }
+
+        let mut grunts = std::mem::take(&mut self.contents.grunts);
+
+        for grunt in grunts.iter_mut() {
+            let persona = match (grunt.persona.take(), &grunt.persona_name) {
+                (Some(persona), None) => persona,
+                (None, Some(name)) => library
+                    .persona(name)
+                    .map_err(|err| self.semantics_err(err))?
+                    .clone(),
+                (Some(_), Some(_)) => {
+                    return Err(self.semantics_err(format!(
+                        "grunt \"{}\" has both a persona and a persona_name; pick one",
+                        grunt.display_name()
+                    )))
+                }
+                (None, None) => {
+                    return Err(self.semantics_err(format!(
+                        "grunt \"{}\" needs either a persona or a persona_name",
+                        grunt.display_name()
+                    )))
+                }
+            };
+
+            let sequence = library.expand_sequence(&persona.sequence).map_err(|err| {
+                self.semantics_err(format!("grunt \"{}\": {}", grunt.display_name(), err))
+            })?;
+
+            for step in &sequence {
+                if let PipelineAction::Named(name, inner) = step {
+                    if !matches!(
+                        **inner,
+                        PipelineAction::LuaFunction(_) | PipelineAction::Combinator(_)
+                    ) {
+                        return Err(self.semantics_err(format!(
+                            "grunt \"{}\": Named(\"{}\", ...) may only wrap a LuaFunction or Combinator (Http actions take a name of their own)",
+                            grunt.display_name(),
+                            name
+                        )));
+                    }
+                }
+            }
+
+            grunt.persona = Some(PersonaSpec {
+                sequence,
+                ..persona
+            });
+        }
+
+        self.contents.grunts = grunts;
+
+        Ok(())
+    }
+
+    /// a human-friendly name for the situation, taken from its file name sans extension
+    pub fn name(&self) -> String {
+        let source = Path::new(&self.source);
+        source
+            .file_stem()
+            .unwrap_or(source.as_os_str())
+            .to_string_lossy()
+            .into()
+    }
+
+    // pins each of the situation's phases (or, lacking any, a single phase lasting as long as
+    // the situation does) to the timeline of the run, with grunt counts multiplied out
+    pub fn phases(
+        &self,
+        grunt_multiplier: usize,
+        default_duration: Option<&ConfigDuration>,
+    ) -> Result<Vec<Phase>, SituationParseErr> {
+        let specs = match &self.contents.phases {
+            None => {
+                let counts: Vec<usize> = self
+                    .contents
+                    .grunts
+                    .iter()
+                    .map(|grunt| grunt.real_count() * grunt_multiplier)
+                    .collect();
+
+                if counts.contains(&0) {
+                    return Err(self.semantics_err("if provided, grunt count must be >=1".into()));
+                }
+
+                return Ok(vec![Phase {
+                    start: Duration::ZERO,
+                    end: None,
+                    counts,
+                }]);
+            }
+            Some(specs) => specs,
+        };
+
+        if specs.is_empty() {
+            return Err(self.semantics_err("if provided, phases must not be empty".into()));
+        }
+
+        if self.contents.duration.is_some() {
+            return Err(self.semantics_err(
+                "a situation with phases takes its duration from them; drop its duration".into(),
+            ));
+        }
+
+        if default_duration.is_some() {
+            eprintln!(
+                "[{}] situation has phases, ignoring --duration",
+                self.name()
+            );
+        }
+
+        let mut phases: Vec<Phase> = Vec::with_capacity(specs.len());
+        let mut start = Duration::ZERO;
+
+        for phase in specs {
+            let end = start + Duration::from(&phase.duration);
+            if end == start {
+                return Err(self.semantics_err(format!(
+                    "phase \"{}\" must last longer than zero seconds",
+                    phase.name.as_deref().unwrap_or("unnamed")
+                )));
+            }
+
+            if let Some(counts) = &phase.counts {
+                for name in counts.keys() {
+                    if !self
+                        .contents
+                        .grunts
+                        .iter()
+                        .any(|g| &g.display_name() == name)
+                    {
+                        return Err(self.semantics_err(format!(
+                            "phase \"{}\" sets a count for grunt \"{}\", which the situation doesn't define",
+                            phase.name.as_deref().unwrap_or("unnamed"),
+                            name
+                        )));
+                    }
+                }
+            }
+
+            phases.push(Phase {
+                start,
+                end: Some(end),
+                counts: self
+                    .contents
+                    .grunts
+                    .iter()
+                    .map(|grunt| phase.count_for(grunt) * grunt_multiplier)
+                    .collect(),
+            });
+            start = end;
+        }
+
+        for (idx, grunt) in self.contents.grunts.iter().enumerate() {
+            if phases.iter().all(|phase| phase.counts[idx] == 0) {
+                return Err(self.semantics_err(format!(
+                    "grunt \"{}\" is not active in any phase",
+                    grunt.display_name()
+                )));
+            }
+        }
+
+        Ok(phases)
+    }
+
+    // for open-model situations, how often a new persona iteration is handed out. that there's a
+    // duration for them to be handed out over is left to Situation::from_spec, as it may come
+    // from the command line
+    pub fn arrival_interval(&self) -> Result<Option<Duration>, SituationParseErr> {
+        match &self.contents.arrival_rate {
+            None => Ok(None),
+            Some(rate) => rate
+                .interval()
+                .map(Some)
+                .ok_or_else(|| self.semantics_err("if provided, arrival_rate must be >=1".into())),
+        }
+    }
+
+    pub fn thresholds(&self) -> Result<Vec<Threshold>, SituationParseErr> {
+        let thresholds = self.contents.thresholds.clone().unwrap_or_default();
+
+        for threshold in &thresholds {
+            if let Some(problem) = threshold.problem() {
+                return Err(self.semantics_err(problem));
+            }
+
+            if let Some(step) = threshold.step() {
+                let exists = self.contents.grunts.iter().any(|grunt| {
+                    let persona = Persona::from(grunt.persona());
+                    persona
+                        .sequence
+                        .iter()
+                        .zip(&persona.step_names)
+                        .any(|(action, name)| {
+                            matches!(action, PipelineAction::Http(_)) && name == step
+                        })
+                });
+
+                if !exists {
+                    return Err(self.semantics_err(format!(
+                        "threshold \"{}\" names step \"{}\", which isn't the name of any Http step in the situation",
+                        threshold, step
+                    )));
+                }
+            }
+        }
+
+        Ok(thresholds)
+    }
+
+    pub fn grunt_specs(&self) -> &[GruntSpec] {
+        &self.contents.grunts
+    }
+
+    /// the names HTTP actions may give as their target
+    pub fn target_names(&self) -> HashSet<&str> {
+        self.contents
+            .targets
+            .iter()
+            .flatten()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    // the situation's own base_url wins over the one given on the command line, while named
+    // targets given on the command line win over the situation's own
+    fn targets(
+        &self,
+        default_base_url: &Url,
+        overrides: &[TargetOverride],
+    ) -> Result<Targets, SituationParseErr> {
+        let base_url = match &self.contents.base_url {
+            None => default_base_url.clone(),
+            Some(url) => parse_base_url(url).map_err(|err| {
+                self.semantics_err(format!("invalid base_url \"{}\": {}", url, err))
+            })?,
+        };
+
+        let mut named = HashMap::new();
+
+        for (name, url) in self.contents.targets.iter().flatten() {
+            named.insert(
+                name.clone(),
+                parse_base_url(url).map_err(|err| {
+                    self.semantics_err(format!(
+                        "invalid URL \"{}\" for target \"{}\": {}",
+                        url, name, err
+                    ))
+                })?,
+            );
+        }
+
+        for over in overrides {
+            named.insert(over.name.clone(), over.url.clone());
+        }
+
+        Ok(Targets { base_url, named })
+    }
+
+    // paths in situation files are relative to the situation file itself
+    fn relative_to_source(&self, file: &str) -> PathBuf {
+        let mut rel_base = PathBuf::from(&self.source);
+        rel_base.pop();
+        rel_base.push(file);
+        rel_base
+    }
+
+    fn semantics_err(&self, message: String) -> SituationParseErr {
+        SituationParseErr {
+            kind: SituationParseErrKind::Semantics {
+                message,
+                location: self.source.clone(),
+            },
+        }
+    }
+
     pub fn canonical_lua_file(&self) -> Option<PathBuf> {
         // this attempts to canonicalize a given string, presuming it's a path to a file.
         // if that fails, it will just pass the given string through to lua unchanged
@@ -57,12 +442,7 @@ impl SituationSpec {
         // native sofile, or whatever). Nones get passed all the way through, skipping the
         // entire song and dance
         self.contents.lua_file.as_ref().map(|file| {
-            canonicalize({
-                let mut rel_base = PathBuf::from(&self.source);
-                rel_base.pop();
-                rel_base.push(file);
-                rel_base
-            }).map_or_else(|_| {
+            canonicalize(self.relative_to_source(file)).map_or_else(|_| {
                 eprintln!("[situation parser] error canonicalizing provided lua_file \"{}\" to a path, passing through to lua unmodified", file);
 
                 PathBuf::from(file)
@@ -71,21 +451,17 @@ impl SituationSpec {
     }
 }
 
-impl FromStr for SituationSpec {
-    type Err = SituationParseErr;
-
-    fn from_str(it: &str) -> Result<Self, Self::Err> {
-        let source = canonicalize(it)?;
-        Ok(Self {
-            contents: DeRon::deserialize_ron(&read_to_string(&source)?)?,
-            source: source.into_os_string().into_string()?,
-        })
-    }
-}
-
 #[derive(Clone, Debug, DeRon)]
 pub struct SituationSpecContents {
     lua_file: Option<String>,
+    includes: Option<Vec<String>>,
+    base_url: Option<String>,
+    targets: Option<HashMap<String, String>>,
+    duration: Option<ConfigDuration>,
+    ramp_up: Option<RampUp>,
+    arrival_rate: Option<ArrivalRate>,
+    phases: Option<Vec<PhaseSpec>>,
+    thresholds: Option<Vec<Threshold>>,
     grunts: Vec<GruntSpec>,
 }
 
@@ -145,3 +521,52 @@ impl From<OsString> for SituationParseErr {
         }
     }
 }
+
+// the fixture the tests of modules checking situation specs build them from
+#[cfg(test)]
+impl SituationSpec {
+    /// the situation in named.ron, with the given contents (in RON) and its personas resolved
+    pub fn test(contents: &str) -> Result<Self, SituationParseErr> {
+        let mut spec = SituationSpec {
+            source: "named.ron".into(),
+            contents: DeRon::deserialize_ron(contents)?,
+        };
+        spec.resolve_personas(&Variables::new(&[]))?;
+
+        Ok(spec)
+    }
+}
+
+#[cfg(test)]
+fn resolve_sequence(sequence: &str) -> Result<SituationSpec, SituationParseErr> {
+    SituationSpec::test(&format!(
+        "(grunts: [(base_name: \"G\", persona: (timeout: Seconds(5), sequence: [{}]))])",
+        sequence
+    ))
+}
+
+#[test]
+fn test_named_wraps_lua_functions_and_combinators() {
+    assert!(resolve_sequence(
+        r#"Named("pick", LuaFunction("pick")), Named("ok", Combinator(AllOf([AssertStatusCode(200)])))"#
+    )
+    .is_ok());
+}
+
+#[test]
+fn test_named_rejects_other_actions() {
+    for step in [
+        r#"Http(Get(url: "items"))"#,
+        "Validator(AssertStatusCode(200))",
+        r#"Include("login")"#,
+    ] {
+        let err = resolve_sequence(&format!("Named(\"nope\", {})", step)).unwrap_err();
+
+        match err.kind {
+            SituationParseErrKind::Semantics { message, .. } => {
+                assert!(message.contains("may only wrap"), "{}: {}", step, message)
+            }
+            kind => panic!("{}: expected a semantics error, got {:?}", step, kind),
+        }
+    }
+}
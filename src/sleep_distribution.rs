@@ -0,0 +1,121 @@
+use nanoserde::DeRon;
+use rand::Rng;
+
+use std::f64::consts::PI;
+use std::time::Duration;
+
+use crate::config_duration::ConfigDuration;
+
+#[cfg(test)]
+use rand::rngs::StdRng;
+#[cfg(test)]
+use rand::SeedableRng;
+
+// the shape of the random pause taken by a RandomSleep step, modelling the time a real user
+// spends reading a page or filling in a form before their next request
+#[derive(Clone, Debug, DeRon)]
+pub enum SleepDistribution {
+    // any duration between min and max, all equally likely
+    Uniform {
+        min: ConfigDuration,
+        max: ConfigDuration,
+    },
+
+    // the bell curve around mean, cut off at zero
+    Normal {
+        mean: ConfigDuration,
+        std_dev: ConfigDuration,
+    },
+
+    // mostly short pauses with the occasional long one, averaging out to mean
+    Exponential {
+        mean: ConfigDuration,
+    },
+}
+
+impl SleepDistribution {
+    pub fn sample(&self, rng: &mut impl Rng) -> Duration {
+        match self {
+            Self::Uniform { min, max } => {
+                let (min, max) = (Duration::from(min), Duration::from(max));
+                if max <= min {
+                    return min;
+                }
+
+                min + (max - min).mul_f64(rng.gen::<f64>())
+            }
+
+            // Box-Muller: 1 - gen() is in (0, 1], keeping ln away from zero
+            Self::Normal { mean, std_dev } => {
+                let z = (-2.0 * (1.0 - rng.gen::<f64>()).ln()).sqrt()
+                    * (2.0 * PI * rng.gen::<f64>()).cos();
+                let secs =
+                    Duration::from(mean).as_secs_f64() + z * Duration::from(std_dev).as_secs_f64();
+
+                Duration::from_secs_f64(secs.max(0.0))
+            }
+
+            Self::Exponential { mean } => {
+                Duration::from(mean).mul_f64(-(1.0 - rng.gen::<f64>()).ln())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn mean_of_samples(dist: &SleepDistribution) -> Duration {
+    let mut rng = StdRng::seed_from_u64(42);
+    let total: Duration = (0..10_000).map(|_| dist.sample(&mut rng)).sum();
+
+    total / 10_000
+}
+
+#[test]
+fn test_uniform_stays_in_bounds() {
+    let dist = SleepDistribution::Uniform {
+        min: ConfigDuration::Milliseconds(100),
+        max: ConfigDuration::Milliseconds(200),
+    };
+    let mut rng = StdRng::seed_from_u64(42);
+
+    for _ in 0..1000 {
+        let sample = dist.sample(&mut rng);
+        assert!(sample >= Duration::from_millis(100), "{:?}", sample);
+        assert!(sample <= Duration::from_millis(200), "{:?}", sample);
+    }
+}
+
+#[test]
+fn test_normal_centers_on_mean() {
+    let mean = mean_of_samples(&SleepDistribution::Normal {
+        mean: ConfigDuration::Seconds(2),
+        std_dev: ConfigDuration::Milliseconds(500),
+    });
+
+    assert!(mean > Duration::from_millis(1950), "{:?}", mean);
+    assert!(mean < Duration::from_millis(2050), "{:?}", mean);
+}
+
+#[test]
+fn test_normal_never_negative() {
+    let dist = SleepDistribution::Normal {
+        mean: ConfigDuration::Milliseconds(0),
+        std_dev: ConfigDuration::Seconds(1),
+    };
+    let mut rng = StdRng::seed_from_u64(42);
+
+    // Duration can't go negative, so this is really checking we don't panic trying
+    for _ in 0..1000 {
+        dist.sample(&mut rng);
+    }
+}
+
+#[test]
+fn test_exponential_averages_to_mean() {
+    let mean = mean_of_samples(&SleepDistribution::Exponential {
+        mean: ConfigDuration::Seconds(1),
+    });
+
+    assert!(mean > Duration::from_millis(950), "{:?}", mean);
+    assert!(mean < Duration::from_millis(1050), "{:?}", mean);
+}
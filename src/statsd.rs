@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::{Error as IOError, ErrorKind as IOErrorKind, Result as IOResult};
+use std::net::{ToSocketAddrs, UdpSocket};
+
+use crate::events::{GruntEvent, RunEvent, StepEvent};
+
+#[cfg(test)]
+use std::time::Duration;
+
+/// sends metrics about each step and grunt to a StatsD agent over UDP as the run goes. plain
+/// StatsD has no notion of tags, so unless speaking DogStatsD, the tags (situation, persona, and
+/// so on) are folded into metric names instead
+#[derive(Debug)]
+pub struct StatsdEmitter {
+    socket: UdpSocket,
+    prefix: String,
+    dogstatsd: bool,
+
+    // keyed by situation and persona, for the active grunts gauge
+    active_grunts: BTreeMap<(String, String), usize>,
+}
+
+impl StatsdEmitter {
+    pub fn connect(addr: &str, prefix: &str, dogstatsd: bool) -> IOResult<Self> {
+        let target = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| IOError::new(IOErrorKind::NotFound, "address resolved to nothing"))?;
+        let socket = UdpSocket::bind(if target.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        })?;
+        socket.connect(target)?;
+
+        Ok(Self {
+            socket,
+            prefix: prefix.into(),
+            dogstatsd,
+            active_grunts: BTreeMap::new(),
+        })
+    }
+
+    /// sends whatever metrics the event makes for as a single packet. StatsD being fire and
+    /// forget, the only errors are those of the local socket (or an ICMP rejection from a
+    /// previous packet)
+    pub fn record(&mut self, event: &RunEvent) -> IOResult<()> {
+        let lines = match event {
+            RunEvent::Step(step) => self.step_lines(step),
+            RunEvent::GruntStarted(grunt) => self.grunt_lines(grunt, true),
+            RunEvent::GruntStopped(grunt) => self.grunt_lines(grunt, false),
+        };
+
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        self.socket.send(lines.join("\n").as_bytes()).map(|_| ())
+    }
+
+    fn step_lines(&self, event: &StepEvent) -> Vec<String> {
+        let step_tags = [
+            ("situation", event.situation.as_str()),
+            ("persona", event.persona.as_str()),
+            ("step", event.name.as_str()),
+        ];
+        let mut lines = Vec::new();
+
+        if event.method.is_some() {
+            let status = event
+                .status_code
+                .map_or_else(|| "error".into(), |status_code| status_code.to_string());
+            let tags = [&step_tags[..], &[("status", status.as_str())]].concat();
+
+            lines.push(self.line("requests", 1, "c", &tags));
+            if event.status_code.is_some() {
+                lines.push(self.line(
+                    "request.duration",
+                    format!("{:.3}", event.latency.as_secs_f64() * 1000.0),
+                    "ms",
+                    &tags,
+                ));
+            }
+        }
+
+        if matches!(event.action, "Validator" | "Combinator") {
+            let outcome = if event.error.is_some() {
+                "fail"
+            } else if !event.warnings.is_empty() {
+                "warn"
+            } else {
+                "pass"
+            };
+            let tags = [&step_tags[..], &[("outcome", outcome)]].concat();
+
+            lines.push(self.line("validations", 1, "c", &tags));
+        }
+
+        lines
+    }
+
+    fn grunt_lines(&mut self, event: &GruntEvent, started: bool) -> Vec<String> {
+        let tags = [
+            ("situation", event.situation.as_str()),
+            ("persona", event.persona.as_str()),
+        ];
+
+        let active = self
+            .active_grunts
+            .entry((event.situation.clone(), event.persona.clone()))
+            .or_default();
+        *active = if started {
+            *active + 1
+        } else {
+            active.saturating_sub(1)
+        };
+        let active = *active;
+
+        vec![
+            self.line(
+                if started {
+                    "grunts.started"
+                } else {
+                    "grunts.stopped"
+                },
+                1,
+                "c",
+                &tags,
+            ),
+            self.line("grunts.active", active, "g", &tags),
+        ]
+    }
+
+    fn line(&self, name: &str, value: impl Display, kind: &str, tags: &[(&str, &str)]) -> String {
+        let mut segments: Vec<String> = Vec::new();
+        if !self.prefix.is_empty() {
+            segments.push(self.prefix.clone());
+        }
+
+        if self.dogstatsd {
+            segments.push(name.into());
+            let tags: Vec<String> = tags
+                .iter()
+                .map(|(tag, value)| format!("{}:{}", tag, sanitize_tag(value)))
+                .collect();
+
+            return format!(
+                "{}:{}|{}|#{}",
+                segments.join("."),
+                value,
+                kind,
+                tags.join(",")
+            );
+        }
+
+        segments.extend(tags.iter().map(|(_, value)| sanitize_segment(value)));
+        segments.push(name.into());
+
+        format!("{}:{}|{}", segments.join("."), value, kind)
+    }
+}
+
+// metric names are dot-separated, and a colon or pipe would end the name early
+fn sanitize_segment(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+// tags are comma-separated, and a pipe or newline would end the line early
+fn sanitize_tag(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            ',' | '|' | '#' | '\n' => '_',
+            _ => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn listen() -> (UdpSocket, String) {
+    let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+    listener
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    (listener, addr)
+}
+
+#[cfg(test)]
+fn receive(listener: &UdpSocket) -> Vec<String> {
+    let mut buf = [0; 4096];
+    let len = listener.recv(&mut buf).unwrap();
+
+    String::from_utf8_lossy(&buf[..len])
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn test_dogstatsd_tags() {
+    let (listener, addr) = listen();
+    let mut statsd = StatsdEmitter::connect(&addr, "seatrial", true).unwrap();
+
+    statsd
+        .record(&RunEvent::Step(StepEvent::test_http(
+            "GET /cart",
+            Some(200),
+            Duration::from_micros(12_500),
+        )))
+        .unwrap();
+    assert_eq!(
+        vec![
+            "seatrial.requests:1|c|#situation:checkout,persona:Buyer,step:GET /cart,status:200",
+            "seatrial.request.duration:12.500|ms|#situation:checkout,persona:Buyer,step:GET /cart,status:200",
+        ],
+        receive(&listener)
+    );
+
+    statsd
+        .record(&RunEvent::Step(StepEvent::test_http(
+            "GET /cart",
+            None,
+            Duration::from_micros(12_500),
+        )))
+        .unwrap();
+    assert_eq!(
+        vec!["seatrial.requests:1|c|#situation:checkout,persona:Buyer,step:GET /cart,status:error"],
+        receive(&listener)
+    );
+
+    statsd
+        .record(&RunEvent::Step(StepEvent {
+            action: "Combinator",
+            warnings: vec!["nope".into()],
+            ..StepEvent::test_validator("AnyOf(a, b)")
+        }))
+        .unwrap();
+    assert_eq!(
+        vec!["seatrial.validations:1|c|#situation:checkout,persona:Buyer,step:AnyOf(a_ b),outcome:warn"],
+        receive(&listener)
+    );
+}
+
+#[test]
+fn test_plain_statsd_folds_tags_into_names() {
+    let (listener, addr) = listen();
+    let mut statsd = StatsdEmitter::connect(&addr, "", false).unwrap();
+    let grunt = GruntEvent {
+        situation: "checkout".into(),
+        persona: "Buyer".into(),
+    };
+
+    statsd
+        .record(&RunEvent::Step(StepEvent::test_http(
+            "GET /cart",
+            Some(404),
+            Duration::from_micros(12_500),
+        )))
+        .unwrap();
+    assert_eq!(
+        vec![
+            "checkout.Buyer.GET__cart.404.requests:1|c",
+            "checkout.Buyer.GET__cart.404.request.duration:12.500|ms",
+        ],
+        receive(&listener)
+    );
+
+    statsd
+        .record(&RunEvent::GruntStarted(grunt.clone()))
+        .unwrap();
+    statsd
+        .record(&RunEvent::GruntStarted(grunt.clone()))
+        .unwrap();
+    statsd.record(&RunEvent::GruntStopped(grunt)).unwrap();
+
+    let lines: Vec<String> = (0..3).flat_map(|_| receive(&listener)).collect();
+    assert_eq!(
+        vec![
+            "checkout.Buyer.grunts.started:1|c",
+            "checkout.Buyer.grunts.active:1|g",
+            "checkout.Buyer.grunts.started:1|c",
+            "checkout.Buyer.grunts.active:2|g",
+            "checkout.Buyer.grunts.stopped:1|c",
+            "checkout.Buyer.grunts.active:1|g",
+        ],
+        lines
+    );
+}
@@ -0,0 +1,318 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::{Duration, SystemTime};
+
+use crate::events::StepEvent;
+use crate::histogram::Histogram;
+
+// the percentiles shown in the summary table, alongside min, mean, and max
+const PERCENTILES: [f64; 4] = [50.0, 90.0, 95.0, 99.0];
+
+/// how the HTTP requests matching some key (a situation, a persona within it, or a single request
+/// of that persona) fared
+#[derive(Clone, Debug, Default)]
+pub struct RequestStats {
+    pub count: u64,
+
+    // requests which failed outright, or got a 4xx or 5xx response
+    pub errors: u64,
+
+    // of requests which got a response, of any status
+    pub latencies: Histogram,
+}
+
+impl RequestStats {
+    /// counts the step in, if it was an HTTP request (and otherwise ignores it)
+    pub fn record(&mut self, event: &StepEvent) {
+        if event.method.is_none() {
+            return;
+        }
+
+        self.count += 1;
+
+        match event.status_code {
+            Some(status_code) => {
+                self.latencies.record(event.latency);
+                if status_code >= 400 {
+                    self.errors += 1;
+                }
+            }
+            None => self.errors += 1,
+        }
+    }
+
+    /// the share (0 to 1) of requests counted as errors
+    pub fn error_rate(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        self.errors as f64 / self.count as f64
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.count += other.count;
+        self.errors += other.errors;
+        self.latencies.merge(&other.latencies);
+    }
+}
+
+/// everything needed for the table printed at the end of a run, built up from the StepEvents
+/// sent back by grunts
+#[derive(Debug, Default)]
+pub struct RunSummary {
+    // keyed by situation, persona, and request
+    requests: BTreeMap<(String, String, String), RequestStats>,
+
+    // open-model situations only, keyed by situation: arrivals that came while every grunt was
+    // busy and there was no room left to hold them
+    dropped_arrivals: BTreeMap<String, u64>,
+
+    // when the first step started and the last one finished, for throughput
+    first: Option<SystemTime>,
+    last: Option<SystemTime>,
+}
+
+impl RunSummary {
+    pub fn record(&mut self, event: &StepEvent) {
+        let finished = event.timestamp + event.latency;
+        self.first = Some(
+            self.first
+                .map_or(event.timestamp, |first| first.min(event.timestamp)),
+        );
+        self.last = Some(self.last.map_or(finished, |last| last.max(finished)));
+
+        if event.method.is_none() {
+            return;
+        }
+
+        self.requests
+            .entry((
+                event.situation.clone(),
+                event.persona.clone(),
+                event.name.clone(),
+            ))
+            .or_default()
+            .record(event);
+    }
+
+    pub fn record_dropped_arrivals(&mut self, situation: &str, count: u64) {
+        if count > 0 {
+            *self.dropped_arrivals.entry(situation.into()).or_default() += count;
+        }
+    }
+
+    pub fn dropped_arrivals(&self, situation: &str) -> u64 {
+        self.dropped_arrivals.get(situation).copied().unwrap_or(0)
+    }
+
+    /// each situation which dropped any arrivals, and how many it dropped
+    pub fn all_dropped_arrivals(&self) -> impl Iterator<Item = (&String, &u64)> {
+        self.dropped_arrivals.iter()
+    }
+
+    /// every request the situation made, or with step given, only those of the so-named step,
+    /// across all personas
+    pub fn stats(&self, situation: &str, step: Option<&str>) -> RequestStats {
+        let mut stats = RequestStats::default();
+        for ((_, _, request), request_stats) in self
+            .requests
+            .iter()
+            .filter(|((request_situation, _, _), _)| request_situation == situation)
+        {
+            if step.map_or(true, |step| step == request) {
+                stats.merge(request_stats);
+            }
+        }
+
+        stats
+    }
+
+    /// each request of each persona of each situation, keyed in that order, and how it fared
+    pub fn requests(&self) -> impl Iterator<Item = (&(String, String, String), &RequestStats)> {
+        self.requests.iter()
+    }
+
+    /// when the first step started, if any did
+    pub fn started(&self) -> Option<SystemTime> {
+        self.first
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// the wall-clock time between the first step starting and the last finishing
+    pub fn elapsed(&self) -> Duration {
+        match (self.first, self.last) {
+            (Some(first), Some(last)) => last.duration_since(first).unwrap_or_default(),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// the summary table: a row for each situation, then under it, one for each of its personas,
+    /// then under each of those, one for each request it made (by step name)
+    pub fn render(&self) -> String {
+        let mut rows: Vec<(String, RequestStats)> = Vec::new();
+        let mut situation_row: Option<usize> = None;
+        let mut persona_row: Option<usize> = None;
+        let mut previous: Option<(&str, &str)> = None;
+
+        for ((situation, persona, request), stats) in &self.requests {
+            if previous.map(|(prev, _)| prev) != Some(situation.as_str()) {
+                situation_row = Some(rows.len());
+                rows.push((situation.clone(), RequestStats::default()));
+                previous = None;
+            }
+
+            if previous.map(|(_, prev)| prev) != Some(persona.as_str()) {
+                persona_row = Some(rows.len());
+                rows.push((format!("  {}", persona), RequestStats::default()));
+            }
+
+            previous = Some((situation, persona));
+
+            for idx in situation_row.iter().chain(&persona_row) {
+                rows[*idx].1.merge(stats);
+            }
+            rows.push((format!("    {}", request), stats.clone()));
+        }
+
+        let label_width = rows
+            .iter()
+            .map(|(label, _)| label.chars().count())
+            .max()
+            .unwrap_or(0);
+        let elapsed = self.elapsed().as_secs_f64();
+
+        let mut out = String::new();
+        let _ = write!(out, "{:<width$}", "", width = label_width);
+        for header in ["count", "errors", "req/s", "min", "mean"] {
+            let _ = write!(out, " {:>8}", header);
+        }
+        for percent in PERCENTILES {
+            let _ = write!(out, " {:>8}", format!("p{}", percent));
+        }
+        let _ = writeln!(out, " {:>8}", "max");
+
+        for (label, stats) in rows {
+            let hist = &stats.latencies;
+            let throughput = if elapsed > 0.0 {
+                stats.count as f64 / elapsed
+            } else {
+                0.0
+            };
+
+            let _ = write!(
+                out,
+                "{:<width$} {:>8} {:>8} {:>8.1}",
+                label,
+                stats.count,
+                stats.errors,
+                throughput,
+                width = label_width
+            );
+
+            if hist.count() == 0 {
+                let _ = writeln!(out);
+                continue;
+            }
+
+            for latency in [hist.min(), hist.mean()]
+                .into_iter()
+                .chain(PERCENTILES.iter().map(|percent| hist.percentile(*percent)))
+                .chain([hist.max()])
+            {
+                let _ = write!(out, " {:>8.1}", latency.as_secs_f64() * 1000.0);
+            }
+            let _ = writeln!(out);
+        }
+
+        for (situation, dropped) in &self.dropped_arrivals {
+            let _ = writeln!(
+                out,
+                "{} dropped {} arrivals, as every grunt was busy",
+                situation, dropped
+            );
+        }
+
+        out
+    }
+}
+
+#[test]
+fn test_rows_roll_up() {
+    let mut summary = RunSummary::default();
+    for (persona, url, status_code, latency_ms) in [
+        ("Buyer", "http://localhost/cart", Some(200), 10),
+        ("Buyer", "http://localhost/cart", Some(500), 30),
+        ("Buyer", "http://localhost/pay", None, 100),
+        ("Browser", "http://localhost/", Some(200), 1000),
+    ] {
+        summary.record(&StepEvent {
+            persona: persona.into(),
+            url: Some(url.into()),
+            ..StepEvent::test_http(
+                &format!("GET {}", url),
+                status_code,
+                Duration::from_millis(latency_ms),
+            )
+        });
+    }
+
+    // everything started at once and the slowest request took a second, so rates match counts.
+    // each row is checked up to its mean latency
+    let expected = [
+        "checkout 4 2 4.0 10.0 346.7",
+        "Browser 1 0 1.0 1000.0 1000.0",
+        "GET http://localhost/ 1 0 1.0 1000.0 1000.0",
+        "Buyer 3 2 3.0 10.0 20.0",
+        "GET http://localhost/cart 2 1 2.0 10.0 20.0",
+        "GET http://localhost/pay 1 1 1.0",
+    ];
+
+    let rendered = summary.render();
+    let rows: Vec<&str> = rendered.lines().skip(1).collect();
+    assert_eq!(expected.len(), rows.len(), "{}", rendered);
+
+    for (row, expected) in rows.iter().zip(expected) {
+        let columns: Vec<&str> = row.split_whitespace().collect();
+        let expected: Vec<&str> = expected.split(' ').collect();
+        assert_eq!(expected, columns[..expected.len()], "{}", rendered);
+    }
+}
+
+#[test]
+fn test_non_http_steps_only_count_towards_elapsed() {
+    let mut summary = RunSummary::default();
+    summary.record(&StepEvent {
+        method: None,
+        url: None,
+        status_code: None,
+        action: "LuaFunction",
+        ..StepEvent::test_http("pick", None, Duration::from_secs(2))
+    });
+
+    assert!(summary.is_empty());
+    assert_eq!(Duration::from_secs(2), summary.elapsed());
+}
+
+#[test]
+fn test_dropped_arrivals_follow_the_table() {
+    let mut summary = RunSummary::default();
+    summary.record(&StepEvent::test_http(
+        "view cart",
+        Some(200),
+        Duration::from_millis(10),
+    ));
+    summary.record_dropped_arrivals("checkout", 0);
+    assert_eq!(0, summary.dropped_arrivals("checkout"));
+    assert!(!summary.render().contains("dropped"));
+
+    summary.record_dropped_arrivals("checkout", 3);
+    assert_eq!(3, summary.dropped_arrivals("checkout"));
+    assert_eq!(
+        Some("checkout dropped 3 arrivals, as every grunt was busy"),
+        summary.render().lines().last()
+    );
+}
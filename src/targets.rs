@@ -0,0 +1,99 @@
+use url::{ParseError, Url};
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// the URLs a situation's HTTP actions are resolved against: the base URL, used by any action
+/// that doesn't name a target, and any number of named targets (say, "auth" or "search") for
+/// situations that exercise more than one service at a time
+#[derive(Clone, Debug)]
+pub struct Targets {
+    pub base_url: Url,
+    pub named: HashMap<String, Url>,
+}
+
+impl Targets {
+    /// the URL an action with the given (optional) target name should be joined onto, or None if
+    /// no such target exists
+    pub fn resolve(&self, target: Option<&str>) -> Option<&Url> {
+        match target {
+            None => Some(&self.base_url),
+            Some(name) => self.named.get(name),
+        }
+    }
+}
+
+/// parses a URL meant to have paths joined onto it. Url::join replaces the last path segment of a
+/// base without a trailing slash (https://example.com/api joined with "users" yields
+/// https://example.com/users), which is never what anyone means here, so one is added as needed
+pub fn parse_base_url(url: &str) -> Result<Url, ParseError> {
+    if url.ends_with('/') {
+        Url::from_str(url)
+    } else {
+        Url::from_str(&format!("{}/", url))
+    }
+}
+
+/// a NAME=URL pair given on the command line, replacing the URL of the named target in every
+/// situation of the run
+#[derive(Clone, Debug)]
+pub struct TargetOverride {
+    pub name: String,
+    pub url: Url,
+}
+
+impl FromStr for TargetOverride {
+    type Err = String;
+
+    fn from_str(it: &str) -> Result<Self, Self::Err> {
+        let (name, url) = it
+            .split_once('=')
+            .ok_or_else(|| format!("expected NAME=URL, got \"{}\"", it))?;
+
+        if name.is_empty() {
+            return Err(format!("expected NAME=URL, got \"{}\"", it));
+        }
+
+        Ok(Self {
+            name: name.into(),
+            url: parse_base_url(url).map_err(|err| format!("invalid URL \"{}\": {}", url, err))?,
+        })
+    }
+}
+
+#[test]
+fn test_parse_base_url_adds_trailing_slash() {
+    let url = parse_base_url("http://localhost:8080/api").unwrap();
+
+    assert_eq!(
+        "http://localhost:8080/api/users",
+        url.join("users").unwrap().as_str()
+    );
+}
+
+#[test]
+fn test_resolve() {
+    let targets = Targets {
+        base_url: parse_base_url("http://api.local").unwrap(),
+        named: HashMap::from([("auth".into(), parse_base_url("http://auth.local").unwrap())]),
+    };
+
+    assert_eq!("http://api.local/", targets.resolve(None).unwrap().as_str());
+    assert_eq!(
+        "http://auth.local/",
+        targets.resolve(Some("auth")).unwrap().as_str()
+    );
+    assert!(targets.resolve(Some("search")).is_none());
+}
+
+#[test]
+fn test_target_override_from_str() {
+    let over = TargetOverride::from_str("auth=http://localhost:9090").unwrap();
+
+    assert_eq!("auth", over.name);
+    assert_eq!("http://localhost:9090/", over.url.as_str());
+
+    assert!(TargetOverride::from_str("http://localhost:9090").is_err());
+    assert!(TargetOverride::from_str("=http://localhost:9090").is_err());
+    assert!(TargetOverride::from_str("auth=not a url").is_err());
+}
@@ -0,0 +1,325 @@
+use nanoserde::DeRon;
+
+use std::fmt::{Display, Formatter, Result as FmtResult, Write};
+use std::time::Duration;
+
+use crate::config_duration::ConfigDuration;
+use crate::summary::RequestStats;
+
+#[cfg(test)]
+use nanoserde::DeRonErr;
+
+/// a pass/fail criterion for a situation's HTTP requests, checked once the run is over, such that
+/// a load test can gate a release on its SLOs. each covers every request the situation made, or
+/// with step given, only those of the so-named Http step (across all of its grunts)
+#[derive(Clone, Debug, DeRon)]
+pub enum Threshold {
+    // the given percentile (0 to 100) of request latencies is under max
+    Latency {
+        percentile: f64,
+        max: ConfigDuration,
+        step: Option<String>,
+    },
+
+    // the share of requests counted as errors (see RequestStats), in percent, is under max
+    ErrorRate {
+        max: f64,
+        step: Option<String>,
+    },
+
+    // requests per second, across the whole run, are at least min
+    Throughput {
+        min: f64,
+        step: Option<String>,
+    },
+}
+
+/// how a threshold fared
+#[derive(Clone, Debug)]
+pub struct Verdict {
+    pub situation: String,
+    pub threshold: String,
+    pub actual: String,
+    pub passed: bool,
+}
+
+impl Verdict {
+    /// the check every open-model situation is held to, thresholds or not: that it delivered
+    /// every arrival its arrival_rate called for, as a run that didn't wasn't the load test
+    /// it was asked to be
+    pub fn arrivals(situation: &str, dropped: u64) -> Self {
+        Self {
+            situation: situation.into(),
+            threshold: "no arrivals dropped".into(),
+            actual: format!("{} dropped", dropped),
+            passed: dropped == 0,
+        }
+    }
+}
+
+impl Threshold {
+    pub fn step(&self) -> Option<&str> {
+        match self {
+            Self::Latency { step, .. }
+            | Self::ErrorRate { step, .. }
+            | Self::Throughput { step, .. } => step.as_deref(),
+        }
+    }
+
+    /// complaints about the threshold's own values, if any, regardless of what it's applied to
+    pub fn problem(&self) -> Option<String> {
+        match self {
+            Self::Latency { percentile, .. } if *percentile <= 0.0 || *percentile > 100.0 => {
+                Some(format!(
+                    "latency threshold percentile must be more than 0 and at most 100, not {}",
+                    percentile
+                ))
+            }
+            Self::ErrorRate { max, .. } if *max < 0.0 => Some(format!(
+                "error rate threshold max must be a percentage of at least 0, not {}",
+                max
+            )),
+            Self::Throughput { min, .. } if *min < 0.0 => Some(format!(
+                "throughput threshold min must be at least 0, not {}",
+                min
+            )),
+            _ => None,
+        }
+    }
+
+    /// checks the threshold against the requests it covers, made over a run of the given
+    /// length. thresholds on latency or error rate fail outright when there's nothing to go by
+    pub fn evaluate(&self, situation: &str, stats: &RequestStats, elapsed: Duration) -> Verdict {
+        let (actual, passed) = match self {
+            Self::Latency {
+                percentile, max, ..
+            } => {
+                if stats.latencies.count() == 0 {
+                    ("no responses".into(), false)
+                } else {
+                    let actual = stats.latencies.percentile(*percentile);
+                    (
+                        format!("{:.1}ms", actual.as_secs_f64() * 1000.0),
+                        actual < Duration::from(max),
+                    )
+                }
+            }
+
+            Self::ErrorRate { max, .. } => {
+                if stats.count == 0 {
+                    ("no requests".into(), false)
+                } else {
+                    let actual = stats.error_rate() * 100.0;
+                    (format!("{:.2}%", actual), actual < *max)
+                }
+            }
+
+            Self::Throughput { min, .. } => {
+                let actual = if elapsed.is_zero() {
+                    0.0
+                } else {
+                    stats.count as f64 / elapsed.as_secs_f64()
+                };
+                (format!("{:.1} req/s", actual), actual >= *min)
+            }
+        };
+
+        Verdict {
+            situation: situation.into(),
+            threshold: self.to_string(),
+            actual,
+            passed,
+        }
+    }
+}
+
+impl Display for Threshold {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let of = match self.step() {
+            Some(step) => format!("of {} ", step),
+            None => String::new(),
+        };
+
+        match self {
+            Self::Latency {
+                percentile, max, ..
+            } => write!(
+                f,
+                "p{} latency {}under {}ms",
+                percentile,
+                of,
+                Duration::from(max).as_secs_f64() * 1000.0
+            ),
+            Self::ErrorRate { max, .. } => write!(f, "error rate {}under {}%", of, max),
+            Self::Throughput { min, .. } => write!(f, "throughput {}at least {} req/s", of, min),
+        }
+    }
+}
+
+/// the pass/fail table printed at the end of a run, a row per threshold
+pub fn render(verdicts: &[Verdict]) -> String {
+    let width = |column: fn(&Verdict) -> &str, header: &str| {
+        verdicts
+            .iter()
+            .map(|verdict| column(verdict).chars().count())
+            .chain([header.len()])
+            .max()
+            .unwrap_or(0)
+    };
+    let situation_width = width(|verdict| &verdict.situation, "situation");
+    let threshold_width = width(|verdict| &verdict.threshold, "threshold");
+    let actual_width = width(|verdict| &verdict.actual, "actual");
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<sw$} {:<tw$} {:>aw$} result",
+        "situation",
+        "threshold",
+        "actual",
+        sw = situation_width,
+        tw = threshold_width,
+        aw = actual_width
+    );
+
+    for verdict in verdicts {
+        let _ = writeln!(
+            out,
+            "{:<sw$} {:<tw$} {:>aw$} {}",
+            verdict.situation,
+            verdict.threshold,
+            verdict.actual,
+            if verdict.passed { "pass" } else { "FAIL" },
+            sw = situation_width,
+            tw = threshold_width,
+            aw = actual_width
+        );
+    }
+
+    out
+}
+
+#[cfg(test)]
+fn stats(latencies_ms: &[u64], errors: u64) -> RequestStats {
+    let mut stats = RequestStats {
+        count: latencies_ms.len() as u64,
+        errors,
+        ..RequestStats::default()
+    };
+    for latency in latencies_ms {
+        stats.latencies.record(Duration::from_millis(*latency));
+    }
+
+    stats
+}
+
+#[test]
+fn test_parse() -> Result<(), DeRonErr> {
+    let thresholds: Vec<Threshold> = DeRon::deserialize_ron(
+        r#"[
+            Latency(percentile: 95, max: Milliseconds(400), step: "checkout"),
+            ErrorRate(max: 1),
+            Throughput(min: 50.5),
+        ]"#,
+    )?;
+
+    let described: Vec<String> = thresholds.iter().map(Threshold::to_string).collect();
+    assert_eq!(
+        vec![
+            "p95 latency of checkout under 400ms",
+            "error rate under 1%",
+            "throughput at least 50.5 req/s",
+        ],
+        described
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_evaluate() {
+    let latency = Threshold::Latency {
+        percentile: 90.0,
+        max: ConfigDuration::Milliseconds(100),
+        step: None,
+    };
+    let elapsed = Duration::from_secs(2);
+
+    let verdict = latency.evaluate("checkout", &stats(&[40; 10], 0), elapsed);
+    assert_eq!(("40.0ms", true), (verdict.actual.as_str(), verdict.passed));
+
+    let stats = stats(&[40, 40, 40, 40, 40, 40, 40, 40, 40, 500], 1);
+    let verdict = latency.evaluate("checkout", &stats, elapsed);
+    assert!(verdict.passed, "{}", verdict.actual);
+
+    let verdict = Threshold::Latency {
+        percentile: 99.0,
+        max: ConfigDuration::Milliseconds(100),
+        step: None,
+    }
+    .evaluate("checkout", &stats, elapsed);
+    assert!(!verdict.passed, "{}", verdict.actual);
+
+    let verdict = Threshold::ErrorRate {
+        max: 10.0,
+        step: None,
+    }
+    .evaluate("checkout", &stats, elapsed);
+    assert_eq!(("10.00%", false), (verdict.actual.as_str(), verdict.passed));
+
+    let verdict = Threshold::Throughput {
+        min: 5.0,
+        step: None,
+    }
+    .evaluate("checkout", &stats, elapsed);
+    assert_eq!(
+        ("5.0 req/s", true),
+        (verdict.actual.as_str(), verdict.passed)
+    );
+}
+
+#[test]
+fn test_nothing_to_go_by_fails() {
+    let verdict = Threshold::ErrorRate {
+        max: 1.0,
+        step: Some("checkout".into()),
+    }
+    .evaluate("checkout", &RequestStats::default(), Duration::from_secs(1));
+
+    assert_eq!(
+        ("no requests", false),
+        (verdict.actual.as_str(), verdict.passed)
+    );
+}
+
+#[test]
+fn test_problem() {
+    assert!(Threshold::Latency {
+        percentile: 0.0,
+        max: ConfigDuration::Milliseconds(100),
+        step: None,
+    }
+    .problem()
+    .is_some());
+    assert!(Threshold::ErrorRate {
+        max: -1.0,
+        step: None,
+    }
+    .problem()
+    .is_some());
+    assert!(Threshold::Throughput {
+        min: 0.0,
+        step: None,
+    }
+    .problem()
+    .is_none());
+}
+
+#[test]
+fn test_arrivals_verdict() {
+    assert!(Verdict::arrivals("checkout", 0).passed);
+
+    let verdict = Verdict::arrivals("checkout", 3);
+    assert!(!verdict.passed);
+    assert_eq!("3 dropped", verdict.actual);
+}
@@ -0,0 +1,324 @@
+use nanoserde::SerJson;
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::events::StepEvent;
+use crate::report::{latency_report, LatencyReport};
+use crate::summary::RequestStats;
+
+#[cfg(test)]
+use nanoserde::DeJson;
+
+/// the file formats --timeseries can be written in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeSeriesFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for TimeSeriesFormat {
+    type Err = String;
+
+    fn from_str(it: &str) -> Result<Self, Self::Err> {
+        match it {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("expected csv or json, got \"{}\"", it)),
+        }
+    }
+}
+
+// how a single step's HTTP requests fared within a single window
+#[derive(Clone, Debug, PartialEq, SerJson)]
+#[cfg_attr(test, derive(DeJson))]
+struct WindowRecord {
+    timestamp_ms: u64,
+    situation: String,
+    persona: String,
+    step: String,
+    count: u64,
+    errors: u64,
+    throughput: f64,
+    latency_ms: Option<LatencyReport>,
+}
+
+#[derive(Clone, Debug, PartialEq, SerJson)]
+#[cfg_attr(test, derive(DeJson))]
+struct TimeSeriesRecord {
+    window_ms: u64,
+    windows: Vec<WindowRecord>,
+}
+
+/// the HTTP requests of each step of each persona of each situation, bucketed into fixed windows
+/// by when they started, for the file written with --timeseries. windows line up with multiples
+/// of their width since the Unix epoch (such that 1s windows start on the second), rather than
+/// with the start of the run
+#[derive(Debug)]
+pub struct TimeSeries {
+    window: Duration,
+
+    // keyed by the start of the window, in milliseconds since the Unix epoch, then situation,
+    // persona, and step name
+    windows: BTreeMap<(u64, String, String, String), RequestStats>,
+}
+
+impl TimeSeries {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            windows: BTreeMap::new(),
+        }
+    }
+
+    /// counts the step in, if it was an HTTP request (and otherwise ignores it)
+    pub fn record(&mut self, event: &StepEvent) {
+        if event.method.is_none() {
+            return;
+        }
+
+        let window_ms = self.window_ms();
+        let started_ms = event
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as u64);
+
+        self.windows
+            .entry((
+                started_ms - started_ms % window_ms,
+                event.situation.clone(),
+                event.persona.clone(),
+                event.name.clone(),
+            ))
+            .or_default()
+            .record(event);
+    }
+
+    // the command line rules out zero-width windows, but there's no sense in dividing by zero
+    // regardless
+    fn window_ms(&self) -> u64 {
+        (self.window.as_millis() as u64).max(1)
+    }
+
+    // a record for every window of each step from the first in which it made a request to the
+    // last, such that windows in between in which it made none show up as such rather than not at
+    // all, ordered by window then situation, persona, and step
+    fn records(&self) -> Vec<WindowRecord> {
+        let window_ms = self.window_ms();
+        let window_secs = window_ms as f64 / 1000.0;
+
+        // keys come in order of window, so each step's last one seen is its last
+        let mut spans: BTreeMap<(&str, &str, &str), (u64, u64)> = BTreeMap::new();
+        for (timestamp_ms, situation, persona, step) in self.windows.keys() {
+            spans
+                .entry((situation, persona, step))
+                .and_modify(|(_, last)| *last = *timestamp_ms)
+                .or_insert((*timestamp_ms, *timestamp_ms));
+        }
+
+        let mut records = Vec::with_capacity(self.windows.len());
+        let mut keys = self.windows.keys();
+        let (first, last) = match (keys.next(), keys.next_back()) {
+            (Some(first), Some(last)) => (first.0, last.0),
+            (Some(only), None) => (only.0, only.0),
+            _ => return records,
+        };
+
+        let no_requests = RequestStats::default();
+        for timestamp_ms in (first..=last).step_by(window_ms as usize) {
+            for ((situation, persona, step), (from, to)) in &spans {
+                if timestamp_ms < *from || timestamp_ms > *to {
+                    continue;
+                }
+
+                let stats = self
+                    .windows
+                    .get(&(
+                        timestamp_ms,
+                        String::from(*situation),
+                        String::from(*persona),
+                        String::from(*step),
+                    ))
+                    .unwrap_or(&no_requests);
+
+                records.push(WindowRecord {
+                    timestamp_ms,
+                    situation: String::from(*situation),
+                    persona: String::from(*persona),
+                    step: String::from(*step),
+                    count: stats.count,
+                    errors: stats.errors,
+                    throughput: stats.count as f64 / window_secs,
+                    latency_ms: latency_report(stats),
+                });
+            }
+        }
+
+        records
+    }
+
+    pub fn render(&self, format: TimeSeriesFormat) -> String {
+        match format {
+            TimeSeriesFormat::Csv => self.render_csv(),
+            TimeSeriesFormat::Json => {
+                let mut out = TimeSeriesRecord {
+                    window_ms: self.window_ms(),
+                    windows: self.records(),
+                }
+                .serialize_json();
+                out.push('\n');
+                out
+            }
+        }
+    }
+
+    // a row per window per step, with latency columns left empty when none of the step's
+    // requests in the window got a response, or it made none
+    fn render_csv(&self) -> String {
+        let mut out = String::from(
+            "timestamp_ms,situation,persona,step,count,errors,throughput,min_ms,mean_ms,p50_ms,p90_ms,p95_ms,p99_ms,max_ms\n",
+        );
+
+        for record in self.records() {
+            let _ = write!(
+                out,
+                "{},{},{},{},{},{},{:.3}",
+                record.timestamp_ms,
+                csv_field(&record.situation),
+                csv_field(&record.persona),
+                csv_field(&record.step),
+                record.count,
+                record.errors,
+                record.throughput
+            );
+
+            match record.latency_ms {
+                Some(latency) => {
+                    for value in [
+                        latency.min,
+                        latency.mean,
+                        latency.p50,
+                        latency.p90,
+                        latency.p95,
+                        latency.p99,
+                        latency.max,
+                    ] {
+                        let _ = write!(out, ",{:.3}", value);
+                    }
+                    out.push('\n');
+                }
+                None => out.push_str(",,,,,,,\n"),
+            }
+        }
+
+        out
+    }
+}
+
+// per RFC 4180, fields holding a comma, quote, or line break are quoted, with quotes doubled
+fn csv_field(value: &str) -> String {
+    if value.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.into()
+    }
+}
+
+#[test]
+fn test_csv() {
+    let mut series = TimeSeries::new(Duration::from_secs(1));
+    for (name, started_ms, status_code) in [
+        ("view cart", 1_650_000_000_999, Some(200)),
+        ("view cart", 1_650_000_000_000, Some(500)),
+        ("view cart", 1_650_000_001_000, None),
+        ("AnyOf(\"a\", b)", 1_650_000_001_500, Some(200)),
+    ] {
+        series.record(&StepEvent {
+            timestamp: UNIX_EPOCH + Duration::from_millis(started_ms),
+            ..StepEvent::test_http(name, status_code, Duration::from_millis(20))
+        });
+    }
+
+    // not a request, and thus not counted
+    series.record(&StepEvent {
+        timestamp: UNIX_EPOCH + Duration::from_millis(1_650_000_001_000),
+        action: "LuaFunction",
+        ..StepEvent::test_validator("view cart")
+    });
+
+    assert_eq!(
+        "timestamp_ms,situation,persona,step,count,errors,throughput,min_ms,mean_ms,p50_ms,p90_ms,p95_ms,p99_ms,max_ms
+1650000000000,checkout,Buyer,view cart,2,1,2.000,20.000,20.000,20.000,20.000,20.000,20.000,20.000
+1650000001000,checkout,Buyer,\"AnyOf(\"\"a\"\", b)\",1,0,1.000,20.000,20.000,20.000,20.000,20.000,20.000,20.000
+1650000001000,checkout,Buyer,view cart,1,1,1.000,,,,,,,
+",
+        series.render(TimeSeriesFormat::Csv)
+    );
+}
+
+#[test]
+fn test_gaps_filled() {
+    let mut series = TimeSeries::new(Duration::from_secs(1));
+    for (name, started_ms, status_code) in [
+        ("view cart", 1_650_000_000_000, Some(200)),
+        ("view cart", 1_650_000_002_000, Some(200)),
+        ("pay", 1_650_000_003_000, Some(200)),
+    ] {
+        series.record(&StepEvent {
+            timestamp: UNIX_EPOCH + Duration::from_millis(started_ms),
+            ..StepEvent::test_http(name, status_code, Duration::from_millis(20))
+        });
+    }
+
+    assert_eq!(
+        "timestamp_ms,situation,persona,step,count,errors,throughput,min_ms,mean_ms,p50_ms,p90_ms,p95_ms,p99_ms,max_ms
+1650000000000,checkout,Buyer,view cart,1,0,1.000,20.000,20.000,20.000,20.000,20.000,20.000,20.000
+1650000001000,checkout,Buyer,view cart,0,0,0.000,,,,,,,
+1650000002000,checkout,Buyer,view cart,1,0,1.000,20.000,20.000,20.000,20.000,20.000,20.000,20.000
+1650000003000,checkout,Buyer,pay,1,0,1.000,20.000,20.000,20.000,20.000,20.000,20.000,20.000
+",
+        series.render(TimeSeriesFormat::Csv)
+    );
+}
+
+#[test]
+fn test_json() {
+    let mut series = TimeSeries::new(Duration::from_secs(10));
+    for (name, started_ms, status_code) in [
+        ("view cart", 1_650_000_009_999, None),
+        ("view cart", 1_650_000_010_000, Some(200)),
+    ] {
+        series.record(&StepEvent {
+            timestamp: UNIX_EPOCH + Duration::from_millis(started_ms),
+            ..StepEvent::test_http(name, status_code, Duration::from_millis(20))
+        });
+    }
+
+    let parsed =
+        TimeSeriesRecord::deserialize_json(&series.render(TimeSeriesFormat::Json)).unwrap();
+    assert_eq!(10_000, parsed.window_ms);
+    assert_eq!(
+        vec![
+            (1_650_000_000_000, 1, 0.1, false),
+            (1_650_000_010_000, 0, 0.1, true)
+        ],
+        parsed
+            .windows
+            .iter()
+            .map(|window| (
+                window.timestamp_ms,
+                window.errors,
+                window.throughput,
+                window.latency_ms.is_some()
+            ))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_parse_format() {
+    assert_eq!(Ok(TimeSeriesFormat::Json), "json".parse());
+    assert!("xml".parse::<TimeSeriesFormat>().is_err());
+}
@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::events::StepEvent;
+
+#[cfg(test)]
+use std::time::UNIX_EPOCH;
+
+// how long the console gets its --warning-rate worth of lines for
+const SAMPLE_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug)]
+struct Tally {
+    count: u64,
+    first: SystemTime,
+    last: SystemTime,
+}
+
+/// every warning raised by a step which otherwise went fine, deduplicated by situation, persona,
+/// step, and message, such that a warning raised on every iteration of every grunt makes for a
+/// single line at the end of the run rather than a flood of them as it goes
+#[derive(Debug, Default)]
+pub struct WarningLog {
+    warnings: BTreeMap<(String, String, String, String), Tally>,
+
+    // how many warnings may be printed as they come in per SAMPLE_WINDOW, if any, and how many
+    // were in the current window (and when it started)
+    rate: u32,
+    window: Option<(Instant, u32)>,
+}
+
+impl WarningLog {
+    pub fn new(rate: u32) -> Self {
+        Self {
+            rate,
+            ..Self::default()
+        }
+    }
+
+    /// tallies the step's warnings, if it raised any, and returns whichever of them should be
+    /// printed right away, given how many already were lately
+    pub fn record(&mut self, event: &StepEvent, now: Instant) -> Vec<String> {
+        let mut sampled = Vec::new();
+
+        for warning in &event.warnings {
+            let tally = self
+                .warnings
+                .entry((
+                    event.situation.clone(),
+                    event.persona.clone(),
+                    event.name.clone(),
+                    warning.clone(),
+                ))
+                .or_insert(Tally {
+                    count: 0,
+                    first: event.timestamp,
+                    last: event.timestamp,
+                });
+            tally.count += 1;
+            tally.first = tally.first.min(event.timestamp);
+            tally.last = tally.last.max(event.timestamp);
+
+            if self.sample(now) {
+                sampled.push(format!(
+                    "[{}] warning during step {}: {}",
+                    event.grunt, event.name, warning
+                ));
+            }
+        }
+
+        sampled
+    }
+
+    fn sample(&mut self, now: Instant) -> bool {
+        if self.rate == 0 {
+            return false;
+        }
+
+        let printed = match &mut self.window {
+            Some((started, printed)) if now.duration_since(*started) < SAMPLE_WINDOW => printed,
+            window => &mut window.insert((now, 0)).1,
+        };
+
+        if *printed < self.rate {
+            *printed += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    /// the table printed at the end of a run, a row per distinct warning, giving how many times
+    /// it was raised, and when it first and last was relative to the start of the run
+    pub fn render(&self, started: SystemTime) -> String {
+        let since = |time: SystemTime| {
+            format!(
+                "{:.1}s",
+                time.duration_since(started)
+                    .unwrap_or_default()
+                    .as_secs_f64()
+            )
+        };
+
+        let rows: Vec<[String; 7]> = self
+            .warnings
+            .iter()
+            .map(|((situation, persona, step, message), tally)| {
+                [
+                    situation.clone(),
+                    persona.clone(),
+                    step.clone(),
+                    tally.count.to_string(),
+                    since(tally.first),
+                    since(tally.last),
+                    message.clone(),
+                ]
+            })
+            .collect();
+
+        let headers = [
+            "situation",
+            "persona",
+            "step",
+            "count",
+            "first",
+            "last",
+            "warning",
+        ];
+        let widths: Vec<usize> = (0..headers.len())
+            .map(|column| {
+                rows.iter()
+                    .map(|row| row[column].chars().count())
+                    .chain([headers[column].len()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let mut out = String::new();
+        let header_row = headers.map(String::from);
+        for row in [&header_row].into_iter().chain(&rows) {
+            let _ = writeln!(
+                out,
+                "{:<sw$} {:<pw$} {:<stw$} {:>cw$} {:>fw$} {:>lw$} {}",
+                row[0],
+                row[1],
+                row[2],
+                row[3],
+                row[4],
+                row[5],
+                row[6],
+                sw = widths[0],
+                pw = widths[1],
+                stw = widths[2],
+                cw = widths[3],
+                fw = widths[4],
+                lw = widths[5]
+            );
+        }
+
+        out
+    }
+}
+
+#[test]
+fn test_render() {
+    let mut log = WarningLog::default();
+    let now = Instant::now();
+
+    for (persona, offset_ms, warnings) in [
+        ("Buyer", 2500, vec![String::from("header X-Foo missing")]),
+        ("Buyer", 1200, vec![String::from("header X-Foo missing")]),
+        ("Buyer", 9000, vec![String::from("header X-Foo missing")]),
+        ("Browser", 300, vec![String::from("header X-Foo missing")]),
+        ("Browser", 300, vec![]),
+    ] {
+        log.record(
+            &StepEvent {
+                timestamp: UNIX_EPOCH + Duration::from_millis(offset_ms),
+                persona: persona.into(),
+                warnings,
+                ..StepEvent::test_validator("WarnUnlessHeaderExists(X-Foo)")
+            },
+            now,
+        );
+    }
+
+    assert_eq!(
+        "situation persona step                          count first last warning
+checkout  Browser WarnUnlessHeaderExists(X-Foo)     1  0.3s 0.3s header X-Foo missing
+checkout  Buyer   WarnUnlessHeaderExists(X-Foo)     3  1.2s 9.0s header X-Foo missing
+",
+        log.render(UNIX_EPOCH)
+    );
+}
+
+#[test]
+fn test_sampling() {
+    let mut log = WarningLog::new(2);
+    let now = Instant::now();
+    let event = StepEvent {
+        warnings: vec!["a".into(), "b".into(), "c".into()],
+        ..StepEvent::test_validator("WarnUnlessHeaderExists(X-Foo)")
+    };
+
+    assert_eq!(
+        vec![
+            "[Buyer 0] warning during step WarnUnlessHeaderExists(X-Foo): a",
+            "[Buyer 0] warning during step WarnUnlessHeaderExists(X-Foo): b",
+        ],
+        log.record(&event, now)
+    );
+    assert!(log
+        .record(&event, now + Duration::from_millis(999))
+        .is_empty());
+    assert_eq!(2, log.record(&event, now + SAMPLE_WINDOW).len());
+}